@@ -0,0 +1,75 @@
+#![no_main]
+
+use avl_cont::binary_search::{ShrinkPolicy, Tree};
+use libfuzzer_sys::fuzz_target;
+use std::collections::BTreeSet;
+
+// Every two bytes are an operation and its operand. Handles are kept from
+// earlier insertions along with their values, so stale ones are looked up
+// after their values are removed, their slots reused or the tree rebuilt.
+fuzz_target!(|data: &[u8]| {
+    let mut tree: Tree<u8> = Tree::default();
+    let mut set = BTreeSet::new();
+    let mut handles = Vec::new();
+
+    for operation in data.chunks_exact(2) {
+        let value = operation[1];
+        match operation[0] % 11 {
+            0 | 1 => {
+                let index = tree.insert(value);
+                assert_eq!(index.is_some(), set.insert(value));
+                handles.extend(index.and_then(|n| tree.handle(n)).map(|h| (h, value)));
+            }
+            2 | 3 => assert_eq!(tree.remove(value), set.take(&value)),
+            4 => assert_eq!(tree.contains(value).is_some(), set.contains(&value)),
+            5 => {
+                let hint = handles
+                    .get(usize::from(value))
+                    .map_or(0, |(h, _)| h.index());
+                let index = tree.insert_hint(hint, value);
+                assert_eq!(index.is_some(), set.insert(value));
+                handles.extend(index.and_then(|n| tree.handle(n)).map(|h| (h, value)));
+            }
+            6 => {
+                for &(handle, value) in &handles {
+                    if let Some(found) = tree.get_handle(handle) {
+                        assert_eq!(found, &value);
+                        assert!(set.contains(found));
+                    }
+                }
+            }
+            7 => {
+                let live: Vec<_> = handles
+                    .iter()
+                    .filter(|&&(h, _)| tree.get_handle(h).is_some())
+                    .copied()
+                    .collect();
+                let remap = tree.compact();
+                for (handle, value) in live {
+                    let index = remap[handle.index()].expect("value was live");
+                    assert_eq!(tree.get(index), Some(&value));
+                }
+            }
+            8 => {
+                let values = (value..=value.saturating_add(value % 32)).step_by(2);
+                set.extend(values.clone());
+                tree.extend_from_sorted(values);
+            }
+            9 => {
+                let keys = (value..=255).step_by(usize::from(value % 8) + 1);
+                let removed = keys.clone().filter(|key| set.remove(key)).count();
+                assert_eq!(tree.remove_all_sorted(keys), removed);
+            }
+            _ => tree.set_shrink_policy(match value % 3 {
+                0 => ShrinkPolicy::Never,
+                1 => ShrinkPolicy::OnDemand,
+                _ => ShrinkPolicy::Aggressive,
+            }),
+        }
+
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.len(), set.len());
+    }
+
+    assert!(tree.iter().eq(set.iter()));
+});
@@ -1,488 +1,2515 @@
 use std::cmp;
-use std::cmp::Ordering;
-use std::collections::VecDeque;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, Write};
+use std::marker::PhantomData;
 use std::mem;
-
+use std::ops::{Bound, Index, RangeBounds};
+use std::vec;
+
+mod augment;
+pub mod bounded;
+pub mod by_key;
+pub mod cache;
+mod compare;
+#[cfg(feature = "concurrent")]
+pub mod concurrent;
+mod cursor;
+mod diff;
+#[cfg(feature = "dot")]
+mod dot;
+mod health;
+mod index;
+mod interval;
+mod linked;
+mod ordered;
+pub mod persistent;
+mod prefix;
+pub mod queue;
+pub mod range_map;
+mod raw;
+mod red_black;
+mod render;
+mod scapegoat;
+mod slab;
+mod snapshot;
+mod sorted_list;
+mod stats;
+mod transaction;
+mod treap;
+mod weight_balanced;
+
+pub use augment::{Augment, Count, Fingerprint, Max, Min, Sum};
+pub use compare::{Compare, Compared, Natural, TotalF32, TotalF64, TotalOrder};
+pub use cursor::{Cursor, CursorMut};
+pub use diff::{Diff, DiffItem, Intersection};
+pub use health::Health;
+pub use index::SlotIndex;
+pub use interval::IntervalTree;
+pub use ordered::OrderedSet;
+pub use prefix::{OrderPrefix, Prefixed};
+pub use raw::RawNode;
+pub use red_black::RbTree;
+pub use scapegoat::ScapegoatTree;
+use slab::Slab;
+pub use snapshot::{SnapshotError, TreeView, ViewRange};
+pub use sorted_list::SortedList;
+use stats::Counters;
+#[cfg(feature = "stats")]
+pub use stats::Stats;
+pub use transaction::Transaction;
+pub use treap::Treap;
+pub use weight_balanced::WbTree;
+
+// Links are `SlotIndex::NONE` when there is no node, which keeps them as
+// narrow as I. Read and write them through the accessors.
 #[derive(Clone)]
-struct Node<T> {
+struct Node<T, I, A> {
     value: T,
-    left: Option<usize>,
-    right: Option<usize>,
+    left: I,
+    right: I,
+    parent: I,
     height: i8,
+    augment: A,
+}
+
+// Returns the fewest nodes an AVL tree of HEIGHT can have, saturating.
+const fn min_nodes(height: i8) -> u128 {
+    let (mut shorter, mut taller) = (0u128, 1u128);
+    let mut h = 0;
+    while h < height {
+        let next = taller.saturating_add(shorter).saturating_add(1);
+        shorter = taller;
+        taller = next;
+        h += 1;
+    }
+    taller
 }
 
-impl<T> Node<T> {
-    const fn new(value: T) -> Self {
+// Heights are stored in an i8, so a tree must never reach a height of
+// i8::MAX. That would take more nodes than a usize can index.
+const _: () = assert!(min_nodes(i8::MAX) > usize::MAX as u128);
+
+impl<T, I: SlotIndex, A: Augment<T>> Node<T, I, A> {
+    fn new(value: T) -> Self {
         Self {
+            augment: A::combine(None, &value, None),
             value,
-            left: None,
-            right: None,
+            left: I::NONE,
+            right: I::NONE,
+            parent: I::NONE,
             height: 0,
         }
     }
 }
 
+// Returns the index LINK refers to, if any.
+fn unpack_link<I: SlotIndex>(link: I) -> Option<usize> {
+    (link != I::NONE).then(|| link.to_usize())
+}
+
+fn pack_link<I: SlotIndex>(index: Option<usize>) -> I {
+    index.map_or(I::NONE, I::from_usize)
+}
+
+impl<T, I: SlotIndex, A> Node<T, I, A> {
+    fn parent(&self) -> Option<usize> {
+        unpack_link(self.parent)
+    }
+
+    fn set_parent(&mut self, index: Option<usize>) {
+        self.parent = pack_link(index);
+    }
+
+    fn left(&self) -> Option<usize> {
+        unpack_link(self.left)
+    }
+
+    fn right(&self) -> Option<usize> {
+        unpack_link(self.right)
+    }
+
+    fn set_left(&mut self, index: Option<usize>) {
+        self.left = pack_link(index);
+    }
+
+    fn set_right(&mut self, index: Option<usize>) {
+        self.right = pack_link(index);
+    }
+
+    // Returns the same node, linked with indices of type J.
+    fn convert<J: SlotIndex>(self) -> Node<T, J, A> {
+        let convert_link = |link: I| pack_link(unpack_link(link));
+        Node {
+            value: self.value,
+            left: convert_link(self.left),
+            right: convert_link(self.right),
+            parent: convert_link(self.parent),
+            height: self.height,
+            augment: self.augment,
+        }
+    }
+}
+
+/// Decides when a tree gives up the slots of removed values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShrinkPolicy {
+    /// Slots are never reused or trimmed, so an index keeps referring to
+    /// the value inserted there, or to nothing once it's removed.
+    Never,
+    /// Slots are reused by insertions, but vacant slots at the end are
+    /// only dropped by `trim`.
+    OnDemand,
+    /// Slots are reused by insertions, and vacant slots at the end are
+    /// dropped after every removal.
+    #[default]
+    Aggressive,
+}
+
+/// Decides what `insert` does with a value equal to one already in the
+/// tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// The new value is dropped and nothing is inserted.
+    #[default]
+    Reject,
+    /// The new value takes the existing one's place and index.
+    ReplaceExisting,
+    /// The new value is inserted after every equal value. Searches find
+    /// any one of the equal values.
+    KeepBoth,
+}
+
+impl DuplicatePolicy {
+    // Pushes VALUE onto VALUES, which are sorted and hold nothing greater,
+    // doing what `insert` would do if it's equal to the last one.
+    fn push_sorted<T: Ord>(self, values: &mut Vec<T>, value: T) {
+        match values.last_mut() {
+            Some(last) if *last == value => match self {
+                Self::Reject => {}
+                Self::ReplaceExisting => *last = value,
+                Self::KeepBoth => values.push(value),
+            },
+            _ => values.push(value),
+        }
+    }
+}
+
+/// Refers to a value by its index, but stops resolving once that value is
+/// removed, even if another value is inserted at the same index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: usize,
+    generation: u32,
+}
+
+impl Handle {
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// Refers to a value by its position in ascending order, counting from 0.
+/// Trees augmented with `Count` can be indexed by it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rank(pub usize);
+
+/// An AVL tree stored in a `Vec`. Nodes link to each other with indices of
+/// type I, which also bounds how many values the tree can hold. Every node
+/// also keeps an A summarizing its subtree, see `Augment`.
+///
+/// Every operation compares values before it moves any node, so if `Ord`
+/// panics, the tree is left as it was before the call.
 #[derive(Clone)]
-pub struct Tree<T> {
-    data: Vec<Option<Node<T>>>,
-    free: Vec<usize>,
+pub struct Tree<T, I = u32, A = ()> {
+    slab: Slab<Node<T, I, A>, I>,
     root: usize,
     size: usize,
+    shrink_policy: ShrinkPolicy,
+    duplicate_policy: DuplicatePolicy,
+    counters: Counters,
+    // The largest value when it was last known. Removing it bumps its
+    // generation, so a stale handle just stops resolving.
+    max: Option<Handle>,
 }
 
+/// A tree whose nodes summarize their subtrees with A, which can be
+/// queried over any range of values with `Tree::query_range`.
+pub type AugmentedTree<T, A> = Tree<T, u32, A>;
+
 impl<T> Default for Tree<T> {
     fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    #[must_use]
+    pub const fn new() -> Self {
         Self {
-            data: Vec::new(),
-            free: Vec::new(),
+            slab: Slab::new(),
             root: 0,
             size: 0,
+            shrink_policy: ShrinkPolicy::Aggressive,
+            duplicate_policy: DuplicatePolicy::Reject,
+            counters: Counters::new(),
+            max: None,
         }
     }
-}
 
-impl<T> Tree<T> {
+    /// Creates an empty tree with room for CAPACITY values.
     #[must_use]
-    pub const fn len(&self) -> usize {
-        self.size
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slab: Slab::with_capacity(capacity),
+            ..Self::new()
+        }
     }
 
+    /// Returns the number of slots the tree can hold without reallocating.
+    /// This counts vacant slots too, so it can be more than `len` values.
     #[must_use]
-    pub const fn is_empty(&self) -> bool {
-        self.size == 0
+    pub fn capacity(&self) -> usize {
+        self.slab.capacity()
+    }
+
+    /// Reserves room for at least ADDITIONAL more slots.
+    pub fn reserve(&mut self, additional: usize) {
+        self.slab.reserve(additional);
+    }
+
+    /// Releases unused memory. Vacant slots at the end of the tree's
+    /// storage are kept unless `trim` is called first.
+    pub fn shrink_to_fit(&mut self) {
+        self.slab.shrink_to_fit();
     }
 
-    /// Returns a reference to the value at INDEX if it exists.
     #[must_use]
-    pub fn get(&self, index: usize) -> Option<&T> {
-        self.data[index].as_ref().map(|n| &n.value)
+    pub const fn shrink_policy(&self) -> ShrinkPolicy {
+        self.shrink_policy
     }
 
-    // Tries to use a free'd index, otherwise pushes.
-    // Returns the index that was used.
-    fn insert_helper(&mut self, value: T) -> usize {
-        if let Some(n) = self.free.pop() {
-            self.data[n] = Some(Node::new(value));
-            n
-        } else {
-            self.data.push(Some(Node::new(value)));
-            self.len()
+    /// Changes when slots of removed values are reused or dropped. Slots
+    /// that are already vacant are left as they are.
+    pub fn set_shrink_policy(&mut self, policy: ShrinkPolicy) {
+        self.shrink_policy = policy;
+    }
+
+    #[must_use]
+    pub const fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicate_policy
+    }
+
+    /// Changes what `insert` does with values that are already in the tree.
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.duplicate_policy = policy;
+    }
+
+    /// Converts the tree to use indices of type J, keeping every value at
+    /// its index. Returns the tree unchanged if J can't address all of its
+    /// slots. Vacant slots count too, so `trim` or `compact` may help.
+    // The counters kept with the `stats` feature make a tree larger.
+    #[cfg_attr(feature = "stats", allow(clippy::result_large_err))]
+    pub fn narrow<J: SlotIndex>(self) -> Result<Tree<T, J, A>, Self> {
+        if self.slab.len() > J::MAX {
+            return Err(self);
         }
+
+        Ok(Tree {
+            slab: self.slab.convert(Node::convert),
+            root: self.root,
+            size: self.size,
+            shrink_policy: self.shrink_policy,
+            duplicate_policy: self.duplicate_policy,
+            counters: self.counters,
+            max: self.max,
+        })
     }
 
-    // Removes None values from the end of the data Vec.
-    // Removes corresponding indices from the free Vec.
-    fn clean_tail(&mut self) {
-        let mut popped = Vec::new();
-        while self.data.last().unwrap().is_none() {
-            self.data.pop();
-            popped.push(self.data.len());
+    /// Converts the tree to use indices of type J, keeping every value at
+    /// its index. J must be able to address at least as many slots as I.
+    #[must_use]
+    pub fn widen<J: SlotIndex>(self) -> Tree<T, J, A> {
+        assert!(J::MAX >= I::MAX, "tree capacity exceeded");
+        match self.narrow() {
+            Ok(tree) => tree,
+            Err(_) => unreachable!(),
         }
-        self.free.retain(|i| {
-            popped.iter().position(|p| i == p).map_or(true, |n| {
-                popped.swap_remove(n);
-                false
-            })
+    }
+
+    /// Drops the vacant slots at the end of the tree's storage.
+    pub fn trim(&mut self) {
+        self.slab.trim();
+    }
+
+    /// Moves every value to the front of the tree's storage, in ascending
+    /// order, and drops all vacant slots. Returns a table mapping each old
+    /// index to the new index of its value, or None if it was vacant.
+    pub fn compact(&mut self) -> Vec<Option<usize>> {
+        let mut iter = Iter::new(self);
+        let order: Vec<usize> = std::iter::from_fn(|| iter.next_index()).collect();
+
+        let relink = |link: Option<usize>, remap: &[Option<usize>]| link.and_then(|n| remap[n]);
+        let remap = self.slab.compact(&order, |node, remap| {
+            node.set_left(relink(node.left(), remap));
+            node.set_right(relink(node.right(), remap));
+            node.set_parent(relink(node.parent(), remap));
         });
+
+        if let Some(root) = remap.get(self.root).copied().flatten() {
+            self.root = root;
+        }
+        remap
     }
 
-    fn update_and_balance(&mut self, mut visited_indices: Vec<usize>) {
-        // This is guarenteed to run at least once.
-        // The last run is always on the root index.
-        while let Some(index) = visited_indices.pop() {
-            let balance_factor = self.update_height(index);
-            let new_parent = self.balance_node(index, balance_factor);
+    /// Removes every value deeper than MAX_DEPTH, where the root is at
+    /// depth 0, and returns them in ascending order. The values that are
+    /// left keep their indices and stay balanced.
+    pub fn prune_depth(&mut self, max_depth: usize) -> Vec<T> {
+        let mut pruned = Vec::new();
+        if self.is_empty() {
+            return pruned;
+        }
+
+        self.prune_subtree(Some(self.root), 0, max_depth, &mut pruned);
+        self.size -= pruned.len();
+        if self.shrink_policy == ShrinkPolicy::Aggressive {
+            self.slab.trim();
+        }
+        pruned
+    }
 
-            // Set the grandfather to point to the subtree's new root.
-            match visited_indices.last() {
-                Some(n) => {
-                    let grandfather_data = self.data[*n].as_ref().unwrap();
-                    let child_is_left = grandfather_data.left.map_or(false, |left| index == left);
-
-                    if child_is_left {
-                        self.data[*n].as_mut().unwrap().left = Some(new_parent);
-                    } else {
-                        self.data[*n].as_mut().unwrap().right = Some(new_parent);
-                    }
+    /// Returns whether OTHER links its nodes into the same shape, no matter
+    /// what values they hold or which indices they use.
+    #[must_use]
+    pub fn shape_eq<U, J: SlotIndex, B>(&self, other: &Tree<U, J, B>) -> bool {
+        let root = |len, root| (len > 0).then_some(root);
+        let mut stack = vec![(root(self.size, self.root), root(other.size, other.root))];
+
+        while let Some(pair) = stack.pop() {
+            match pair {
+                (None, None) => (),
+                (Some(a), Some(b)) => {
+                    let (a, b) = (&self.slab[a], &other.slab[b]);
+                    stack.push((a.left(), b.left()));
+                    stack.push((a.right(), b.right()));
                 }
-                None => self.root = new_parent,
+                _ => return false,
             }
         }
+        true
     }
 
-    // Update a node's height to be 1 + max_height between its children.
-    // If a node has no children, its height is calculated as 1 + -1 = 0.
-    // Returns the balance factor of the node. This is calcuated as the
-    // difference between its children's heights. If the right child has
-    // a height of 3 while the left has 1, the balance factor would be
-    // calculated as 2. This value or its inverse would require the tree
-    // to be rebalanced.
-    fn update_height(&mut self, index: usize) -> i8 {
-        let node_data = self.data[index].as_ref().unwrap();
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.size
+    }
 
-        let left_height: i8 = match node_data.left {
-            Some(n) => self.data[n].as_ref().unwrap().height,
-            None => -1,
-        };
-        let right_height: i8 = match node_data.right {
-            Some(n) => self.data[n].as_ref().unwrap().height,
-            None => -1,
-        };
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0
+    }
 
-        let node_data = self.data[index].as_mut().unwrap();
-        node_data.height = 1 + cmp::max(left_height, right_height);
+    /// Returns the summary of every value in the tree, or None if it's
+    /// empty.
+    #[must_use]
+    pub fn augment(&self) -> Option<&A> {
+        (!self.is_empty()).then(|| &self.slab[self.root].augment)
+    }
 
-        right_height - left_height
+    /// Calls F on every value in ascending order. Unlike `iter`, it
+    /// doesn't allocate, as it follows parent links to move up the tree.
+    pub fn for_each_in_order<F: FnMut(&T)>(&self, mut f: F) {
+        self.fold((), |(), value| f(value));
     }
 
-    // Balance a node if one of its sides is two nodes taller than the other.
-    // In a sequence where the nodes connect A -> B -> C, a rotation is done
-    // such that node B points to its parent: A <- B -> C. If node B does not
-    // have a child in the same direction: A -> B and C <- B, node A is set
-    // to point to node C and C to B: A -> C -> B, then node C is set to
-    // point to node A: A <- C -> B. Returns the new parent's index.
-    fn balance_node(&mut self, index: usize, balance_factor: i8) -> usize {
-        let node_data = self.data[index].as_ref().unwrap();
-        match balance_factor {
-            -2 => {
-                let left_node = self.data[node_data.left.unwrap()].as_ref().unwrap();
-                match left_node.left {
-                    Some(_) => self.rotate_right(index),
-                    None => self.rotate_left_right(index),
-                }
-            }
-            2 => {
-                let right_node = self.data[node_data.right.unwrap()].as_ref().unwrap();
-                match right_node.right {
-                    Some(_) => self.rotate_left(index),
-                    None => self.rotate_right_left(index),
+    /// Combines every value in ascending order with an accumulator that
+    /// starts as INIT, like `Iterator::fold`. Doesn't allocate, like
+    /// `for_each_in_order`.
+    pub fn fold<B, F: FnMut(B, &T) -> B>(&self, init: B, mut f: F) -> B {
+        let mut accumulator = init;
+        if self.is_empty() {
+            return accumulator;
+        }
+
+        let mut current = Some(self.min_index(self.root));
+        while let Some(index) = current {
+            accumulator = f(accumulator, &self.slab[index].value);
+            current = self.successor(index);
+        }
+        accumulator
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_, T, I, A> {
+        Iter::new(self)
+    }
+
+    /// Returns a cursor at the smallest value, or past the ends if the
+    /// tree is empty.
+    #[must_use]
+    pub fn cursor_front(&self) -> Cursor<'_, T, I, A> {
+        Cursor::new(self, self.next_wrapping(None))
+    }
+
+    /// Returns a cursor at the largest value, or past the ends if the
+    /// tree is empty.
+    #[must_use]
+    pub fn cursor_back(&self) -> Cursor<'_, T, I, A> {
+        Cursor::new(self, self.prev_wrapping(None))
+    }
+
+    /// Checks that the values in ascending order are exactly EXPECTED,
+    /// stopping at the first value that differs or at the first position
+    /// where only one side still has values.
+    pub fn validate_against<V>(&self, expected: V) -> Result<(), Divergence<'_, T>>
+    where
+        T: PartialEq,
+        V: IntoIterator<Item = T>,
+    {
+        let mut expected = expected.into_iter();
+
+        for (index, found) in self.iter().enumerate() {
+            match expected.next() {
+                Some(expected) if expected == *found => {}
+                Some(expected) => {
+                    return Err(Divergence::Value {
+                        index,
+                        expected,
+                        found,
+                    })
                 }
+                None => return Err(Divergence::Extra { index, found }),
             }
-            _ => index,
+        }
+
+        match expected.next() {
+            Some(expected) => Err(Divergence::Missing {
+                index: self.size,
+                expected,
+            }),
+            None => Ok(()),
         }
     }
 
-    fn rotate_right(&mut self, index: usize) -> usize {
-        let left_index = self.data[index].as_ref().unwrap().left.unwrap();
-        let left_right_index = self.data[left_index].as_ref().unwrap().right;
+    /// Writes the values to WRITER in ascending order, with DELIMITER
+    /// between them. FORMAT appends the text of a value to the buffer it is
+    /// given, which is handed to WRITER in large chunks.
+    pub fn write_sorted<W, F>(
+        &self,
+        mut writer: W,
+        delimiter: &[u8],
+        mut format: F,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        F: FnMut(&T, &mut Vec<u8>),
+    {
+        const CHUNK_SIZE: usize = 64 * 1024;
+        let mut buffer = Vec::with_capacity(CHUNK_SIZE);
+
+        for (n, value) in self.iter().enumerate() {
+            if n > 0 {
+                buffer.extend_from_slice(delimiter);
+            }
+            format(value, &mut buffer);
 
-        let node_data = self.data[index].as_mut().unwrap();
-        node_data.left = left_right_index;
+            if buffer.len() >= CHUNK_SIZE {
+                writer.write_all(&buffer)?;
+                buffer.clear();
+            }
+        }
 
-        let left_data = self.data[left_index].as_mut().unwrap();
-        left_data.right = Some(index);
+        writer.write_all(&buffer)?;
+        writer.flush()
+    }
 
-        self.update_height(index);
-        self.update_height(left_index);
+    /// Returns every index whose value or links changed since the last call
+    /// to `clear_dirty`, in ascending order, along with its current value.
+    /// Indices that were vacated have no value.
+    pub fn dirty_slots(&self) -> impl Iterator<Item = (usize, Option<&T>)> + '_ {
+        self.slab.dirty().map(|n| (n, self.get(n)))
+    }
 
-        left_index
+    /// Marks every index as unchanged.
+    pub fn clear_dirty(&mut self) {
+        self.slab.clear_dirty();
     }
 
-    fn rotate_left(&mut self, index: usize) -> usize {
-        let right_index = self.data[index].as_ref().unwrap().right.unwrap();
-        let right_left_index = self.data[right_index].as_ref().unwrap().left;
+    /// Returns a reference to the value at INDEX if it exists.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.slab.get(index).map(|n| &n.value)
+    }
 
-        let node_data = self.data[index].as_mut().unwrap();
-        node_data.right = right_left_index;
+    /// Returns a reference to the value at INDEX without checking that it
+    /// exists.
+    ///
+    /// # Safety
+    ///
+    /// INDEX must hold a value, as checked by `is_occupied`.
+    #[must_use]
+    pub unsafe fn get_unchecked(&self, index: usize) -> &T {
+        // SAFETY: the caller guarantees that INDEX is occupied.
+        unsafe { &self.slab.get_unchecked(index).value }
+    }
 
-        let right_data = self.data[right_index].as_mut().unwrap();
-        right_data.left = Some(index);
+    /// Returns whether INDEX holds a value.
+    #[must_use]
+    pub fn is_occupied(&self, index: usize) -> bool {
+        self.slab.get(index).is_some()
+    }
 
-        self.update_height(index);
-        self.update_height(right_index);
+    /// Returns the index of the next larger value after the one at INDEX,
+    /// or None if it's the largest or INDEX holds no value. Takes O(1)
+    /// amortized over a walk through the tree.
+    #[must_use]
+    pub fn next_index(&self, index: usize) -> Option<usize> {
+        self.slab.get(index)?;
+        self.successor(index)
+    }
 
-        right_index
+    /// Returns the index of the next smaller value before the one at
+    /// INDEX, or None if it's the smallest or INDEX holds no value.
+    #[must_use]
+    pub fn prev_index(&self, index: usize) -> Option<usize> {
+        self.slab.get(index)?;
+        self.predecessor(index)
     }
 
-    fn rotate_left_right(&mut self, index: usize) -> usize {
-        let left_index = self.data[index].as_ref().unwrap().left.unwrap();
+    /// Returns an iterator over the values in the subtree rooted at INDEX,
+    /// in ascending order. It's empty if INDEX holds no value.
+    pub fn iter_subtree(&self, index: usize) -> impl Iterator<Item = &T> {
+        let bounds = self
+            .slab
+            .get(index)
+            .map(|_| (self.min_index(index), self.max_index(index)));
+        let first = bounds.map(|(first, _)| first);
+        std::iter::successors(first, move |&n| match bounds {
+            Some((_, last)) if n != last => self.successor(n),
+            _ => None,
+        })
+        .map(|n| &self.slab[n].value)
+    }
 
-        self.data[index].as_mut().unwrap().left = Some(self.rotate_left(left_index));
-        self.rotate_right(index)
+    /// Returns a handle to the value at INDEX if it exists.
+    #[must_use]
+    pub fn handle(&self, index: usize) -> Option<Handle> {
+        self.get(index)?;
+        Some(Handle {
+            index,
+            generation: self.slab.generation(index),
+        })
     }
 
-    fn rotate_right_left(&mut self, index: usize) -> usize {
-        let right_index = self.data[index].as_ref().unwrap().right.unwrap();
+    /// Returns a reference to the value HANDLE refers to, or None if that
+    /// value was removed. Handles also stop resolving once their value may
+    /// have moved to another index: after `compact`, after
+    /// `extend_from_sorted` or `remove_all_sorted` rebuild the tree, after
+    /// `concat_disjoint` copies this tree's values next to the other's or
+    /// `join` does so with LEFT's, and after `split_at_rank`,
+    /// `truncate_smallest` or `truncate_largest` move the values this tree
+    /// keeps into new storage.
+    #[must_use]
+    pub fn get_handle(&self, handle: Handle) -> Option<&T> {
+        let value = self.get(handle.index)?;
+        (self.slab.generation(handle.index) == handle.generation).then_some(value)
+    }
 
-        self.data[index].as_mut().unwrap().right = Some(self.rotate_right(right_index));
-        self.rotate_left(index)
+    /// Builds a tree from VALUES without sorting them. VALUES must be in
+    /// ascending order and contain no duplicates, otherwise the tree is
+    /// left in an invalid state. Each value's position becomes its index.
+    #[must_use]
+    pub fn from_sorted_unchecked(values: Vec<T>) -> Self {
+        let size = values.len();
+        let mut tree = Self {
+            slab: Slab::from_vec(values.into_iter().map(Node::new).collect()),
+            root: 0,
+            size,
+            shrink_policy: ShrinkPolicy::Aggressive,
+            duplicate_policy: DuplicatePolicy::Reject,
+            counters: Counters::new(),
+            max: None,
+        };
+
+        if let Some(root) = tree.build_range(0, size) {
+            tree.root = root;
+        }
+        tree
     }
-}
 
-impl<T: Ord> Tree<T> {
-    /// Returns the index of VALUE if it is found.
-    pub fn contains(&self, value: T) -> Option<usize> {
-        if self.is_empty() {
-            return None;
+    /// Moves the values out in ascending order. Takes O(n).
+    #[must_use]
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut indices = Vec::with_capacity(self.size);
+        let mut iter = Iter::new(&self);
+        while let Some(index) = iter.next_index() {
+            indices.push(index);
         }
 
-        let parent_index = match self.contains_helper(&value) {
-            (true, Some(n)) => *n.last().unwrap(),
-            (true, None) => return Some(self.root),
-            (false, _) => return None,
-        };
-        let parent_data = self.data[parent_index].as_ref().unwrap();
+        indices
+            .into_iter()
+            .map(|index| self.slab.remove(index).value)
+            .collect()
+    }
 
-        match value.cmp(&parent_data.value) {
-            Ordering::Less => parent_data.left,
-            Ordering::Greater => parent_data.right,
-            Ordering::Equal => unreachable!(),
-        }
+    /// Returns a tree of F applied to every value, with the same shape,
+    /// indices and policies. F must keep the values in the same order, as
+    /// the tree isn't rebuilt. It's called in index order. The new tree
+    /// has no augments, since they would need recomputing.
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> Tree<U, I> {
+        self.convert_values(f, false)
     }
 
-    // Returns a bool and all visited indices up to
-    // and including the (prospective) parent index.
-    fn contains_helper(&self, value: &T) -> (bool, Option<Vec<usize>>) {
-        let mut current_index = self.root;
-        let mut current_data = self.data[current_index].as_ref().unwrap();
+    /// Returns a tree of the same values in descending order, so that the
+    /// largest comes first. Each value keeps its index. Takes O(n), as the
+    /// tree is mirrored rather than rebuilt. The new tree has no augments.
+    pub fn reversed(self) -> Tree<Reverse<T>, I> {
+        self.convert_values(Reverse, true)
+    }
 
-        if value == &current_data.value {
-            return (true, None);
+    // Returns a tree of F applied to every value, with the same shape or,
+    // if MIRROR, the mirror image of it, and no augments.
+    fn convert_values<U, F: FnMut(T) -> U>(self, mut f: F, mirror: bool) -> Tree<U, I> {
+        Tree {
+            slab: self.slab.convert(|node| {
+                let (left, right) = match mirror {
+                    true => (node.right, node.left),
+                    false => (node.left, node.right),
+                };
+                Node {
+                    value: f(node.value),
+                    left,
+                    right,
+                    parent: node.parent,
+                    height: node.height,
+                    augment: (),
+                }
+            }),
+            root: self.root,
+            size: self.size,
+            shrink_policy: self.shrink_policy,
+            duplicate_policy: self.duplicate_policy,
+            counters: Counters::new(),
+            max: None,
         }
+    }
 
-        let mut visited_indices = vec![current_index];
+    /// Returns a balanced tree of the values F maps to Some, which may be
+    /// in any order. F is called in ascending order. Equal results are
+    /// handled by the duplicate policy as if inserted in that order.
+    /// Takes O(n log n).
+    pub fn filter_map<U: Ord, F: FnMut(T) -> Option<U>>(self, f: F) -> Tree<U, I> {
+        let policy = self.duplicate_policy;
+        let mut mapped: Vec<U> = self.into_sorted_vec().into_iter().filter_map(f).collect();
+        // A stable sort, so that equal results keep their order.
+        mapped.sort();
+
+        let mut values = Vec::with_capacity(mapped.len());
+        for value in mapped {
+            policy.push_sorted(&mut values, value);
+        }
+        let mut tree = Tree::from_sorted_unchecked(values);
+        tree.duplicate_policy = policy;
+        tree
+    }
 
-        // Returns false if the value must be a child of a node
-        // that lacks a child on the correct side.
-        while current_data.left.is_some() || current_data.right.is_some() {
-            current_index = match value.cmp(&current_data.value) {
-                Ordering::Less => match current_data.left {
-                    Some(n) => n,
-                    None => return (false, Some(visited_indices)),
-                },
-                Ordering::Greater => match current_data.right {
-                    Some(n) => n,
-                    None => return (false, Some(visited_indices)),
-                },
-                Ordering::Equal => current_index,
-            };
+    /// Splits the tree into a tree of the values for which F returns true
+    /// and a tree of the rest, both with this tree's policies. F is called
+    /// in ascending order, and both trees are built in O(n) without
+    /// inserting. With `ShrinkPolicy::Never` the rest are removed one by
+    /// one instead, so the values F returns true for keep their indices.
+    pub fn partition<F: FnMut(&T) -> bool>(mut self, mut f: F) -> (Self, Self) {
+        let (shrink_policy, duplicate_policy) = (self.shrink_policy, self.duplicate_policy);
+        if shrink_policy == ShrinkPolicy::Never {
+            let mut order = Vec::with_capacity(self.size);
+            let mut iter = Iter::new(&self);
+            while let Some(n) = iter.next_index() {
+                order.push(n);
+            }
 
-            current_data = self.data[current_index].as_ref().unwrap();
-            if value == &current_data.value {
-                return (true, Some(visited_indices));
+            let mut rest = Vec::new();
+            for index in order {
+                if !f(&self.slab[index].value) {
+                    rest.push(self.remove_index(index));
+                }
             }
+            let mut rest = Self::from_sorted_unchecked(rest);
+            rest.shrink_policy = shrink_policy;
+            rest.duplicate_policy = duplicate_policy;
+            return (self, rest);
+        }
+
+        let (matching, rest): (Vec<T>, Vec<T>) = self
+            .into_sorted_vec()
+            .into_iter()
+            .partition(|value| f(value));
 
-            visited_indices.push(current_index);
+        let [mut matching, mut rest] = [matching, rest].map(Self::from_sorted_unchecked);
+        for tree in [&mut matching, &mut rest] {
+            tree.shrink_policy = shrink_policy;
+            tree.duplicate_policy = duplicate_policy;
         }
+        (matching, rest)
+    }
 
-        (false, Some(visited_indices))
+    /// Removes every value for which F returns false, visiting them in
+    /// ascending order. The values that are kept stay at their indices.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        self.retain_by(|value| f(value));
     }
 
-    /// Insert VALUE into the tree. Must be unique. Returns the index that was
-    /// used, or None if it wasn't inserted.
-    pub fn insert(&mut self, value: T) -> Option<usize> {
-        if self.is_empty() {
-            self.data.push(Some(Node::new(value)));
-            self.size = 1;
-            return Some(0);
+    // Like `retain`, but F may change the values it keeps, which must
+    // stay in order and leave any augments correct.
+    fn retain_by<F: FnMut(&mut T) -> bool>(&mut self, mut f: F) {
+        let mut order = Vec::with_capacity(self.size);
+        let mut iter = Iter::new(self);
+        while let Some(n) = iter.next_index() {
+            order.push(n);
         }
 
-        let (false, Some(visited_indices)) = self.contains_helper(&value) else {
-            return None;
-        };
-        let parent_index = *visited_indices.last().unwrap();
-        let insert_index;
-
-        match &value.cmp(&self.data[parent_index].as_ref().unwrap().value) {
-            Ordering::Less => {
-                insert_index = self.insert_helper(value);
-                self.data[parent_index].as_mut().unwrap().left = Some(insert_index);
+        for index in order {
+            if !f(&mut self.slab[index].value) {
+                self.remove_index(index);
             }
-            Ordering::Greater => {
-                insert_index = self.insert_helper(value);
-                self.data[parent_index].as_mut().unwrap().right = Some(insert_index);
-            }
-            Ordering::Equal => unreachable!(),
         }
+    }
 
-        self.update_and_balance(visited_indices);
-        self.size += 1;
-        Some(insert_index)
+    // Stores NODE in a slot allowed by the shrink policy.
+    // Returns the index that was used.
+    fn insert_node(&mut self, node: Node<T, I, A>) -> usize {
+        let len = self.slab.len();
+        let index = match self.shrink_policy {
+            ShrinkPolicy::Never => self.slab.push(node),
+            ShrinkPolicy::OnDemand | ShrinkPolicy::Aggressive => self.slab.insert(node),
+        };
+        match index < len {
+            true => self.counters.free_list_hit(),
+            false => self.counters.allocation(),
+        }
+        index
     }
 
-    /// Remove VALUE from the tree.
-    pub fn remove(&mut self, value: T) -> Option<T> {
-        if self.is_empty() {
+    // Links the indices START..END into a balanced subtree and returns the
+    // index of its root. The middle index is used as the root so that both
+    // sides differ in size by at most one.
+    fn build_range(&mut self, start: usize, end: usize) -> Option<usize> {
+        if start == end {
             return None;
         }
 
-        let is_root = match self.remove_root_helper(&value) {
-            (b, None) => b,
-            (true, Some(return_val)) => return Some(return_val),
-            _ => unreachable!(),
-        };
+        let mid = start + (end - start) / 2;
+        let left = self.build_range(start, mid);
+        let right = self.build_range(mid + 1, end);
 
-        let mut visited_indices = match self.contains_helper(&value) {
-            (true, None) => vec![self.root],
-            (true, Some(n)) => n,
-            (false, _) => return None,
-        };
-        let parent_index = *visited_indices.last().unwrap();
-        let parent_data = self.data[parent_index].as_ref().unwrap();
-        let child_is_left = value < parent_data.value;
-
-        let val_index = match (is_root, child_is_left) {
-            (true, _) => self.root,
-            (false, true) => parent_data.left.unwrap(),
-            (false, false) => parent_data.right.unwrap(),
-        };
-        let val_data = self.data[val_index].as_ref().unwrap();
-        let return_val;
-
-        if let (Some(val_left), Some(val_right)) = (val_data.left, val_data.right) {
-            let mut left_data = self.data[val_left].as_ref().unwrap();
-            let mut right_data = self.data[val_right].as_ref().unwrap();
-
-            let mut current_index;
-
-            // The node being removed will have its child tree modified.
-            // So it must be added to the visited indices to be updated
-            // and balanced. This is also done for every node visited
-            // up to the replacement. The root index was already added.
-            if !is_root {
-                visited_indices.push(val_index);
-            }
-
-            // Climbs to the replacement node on whichever side
-            // has the greater height. If it doesn't have a child,
-            // its parent's pointer is set to None.
-            let replace_index = if left_data.height > right_data.height {
-                current_index = val_left;
-                while let Some(n) = left_data.right {
-                    visited_indices.push(current_index);
-                    current_index = n;
-                    left_data = self.data[current_index].as_ref().unwrap();
-                }
+        self.set_children(mid, left, right);
+        self.update_height(mid);
 
-                if let Some(n) = left_data.left {
-                    self.data.swap(current_index, n);
-                    n
-                } else {
-                    self.data[*visited_indices.last().unwrap()]
-                        .as_mut()
-                        .unwrap()
-                        .right = None;
-                    current_index
-                }
-            } else {
-                current_index = val_right;
-                while let Some(n) = right_data.left {
-                    visited_indices.push(current_index);
-                    current_index = n;
-                    right_data = self.data[current_index].as_ref().unwrap();
-                }
+        Some(mid)
+    }
 
-                if let Some(n) = right_data.right {
-                    self.data.swap(current_index, n);
-                    n
-                } else {
-                    self.data[*visited_indices.last().unwrap()]
-                        .as_mut()
-                        .unwrap()
-                        .left = None;
-                    current_index
-                }
-            };
-            self.free.push(replace_index);
-            let replace = Some(Node {
-                value: mem::replace(&mut self.data[replace_index], None)
-                    .unwrap()
-                    .value,
-                // If the value at the index is None, set pointer to None.
-                left: self.data[val_left].as_ref().map(|_| val_left),
-                right: self.data[val_right].as_ref().map(|_| val_right),
-                // The height of this new Node doesn't matter
-                // since it will be updated.
-                height: 0,
-            });
-
-            return_val = mem::replace(&mut self.data[val_index], replace)
-                .unwrap()
-                .value;
-        } else {
-            if let Some(child_index) = val_data.left.xor(val_data.right) {
-                if child_is_left {
-                    self.data[parent_index].as_mut().unwrap().left = Some(child_index);
+    // Points the node at INDEX to LEFT and RIGHT, and both of them back.
+    fn set_children(&mut self, index: usize, left: Option<usize>, right: Option<usize>) {
+        let node_data = &mut self.slab[index];
+        node_data.set_left(left);
+        node_data.set_right(right);
+
+        for child in [left, right].into_iter().flatten() {
+            self.slab[child].set_parent(Some(index));
+        }
+    }
+
+    // Makes NEW take the place of OLD as a child of PARENT. Without a
+    // parent, NEW becomes the root.
+    fn replace_child(&mut self, parent: Option<usize>, old: usize, new: Option<usize>) {
+        match parent {
+            Some(n) => {
+                let parent_data = &mut self.slab[n];
+                if parent_data.left() == Some(old) {
+                    parent_data.set_left(new);
                 } else {
-                    self.data[parent_index].as_mut().unwrap().right = Some(child_index);
+                    parent_data.set_right(new);
                 }
-            } else if child_is_left {
-                self.data[parent_index].as_mut().unwrap().left = None;
-            } else {
-                self.data[parent_index].as_mut().unwrap().right = None;
             }
-
-            self.free.push(val_index);
-            return_val = mem::replace(&mut self.data[val_index], None).unwrap().value;
+            None => self.root = new.unwrap_or(0),
         }
 
-        self.update_and_balance(visited_indices);
-        self.clean_tail();
-        self.size -= 1;
-        Some(return_val)
+        if let Some(n) = new {
+            self.slab[n].set_parent(parent);
+        }
     }
 
-    // Handling trivial cases for removing a value at root.
-    fn remove_root_helper(&mut self, value: &T) -> (bool, Option<T>) {
-        let root_data = self.data[self.root].as_ref().unwrap();
-        if value == &root_data.value {
-            if self.size == 1 {
-                let return_val = self.data.pop().unwrap().unwrap().value;
-                self.free.clear();
-                self.data.clear();
-                self.root = 0;
-                self.size = 0;
+    // Walks up from INDEX, updating and balancing every node on the way,
+    // until a subtree's height and augment are unchanged. If the walk
+    // reaches the top, the last node visited becomes the root.
+    fn update_and_balance(&mut self, index: usize) {
+        self.update_and_balance_through(index, None);
+    }
 
-                return (true, Some(return_val));
+    // Like `update_and_balance`, but keeps walking at least until THROUGH
+    // has been updated.
+    fn update_and_balance_through(&mut self, mut index: usize, mut through: Option<usize>) {
+        loop {
+            let old_height = self.slab[index].height;
+            let old_augment = self.slab[index].augment.clone();
+            let balance_factor = self.update_height(index);
+            let new_parent = self.balance_node(index, balance_factor);
+            if through == Some(index) {
+                through = None;
             }
 
-            if let Some(new_root) = root_data.left.xor(root_data.right) {
-                let return_val = mem::replace(&mut self.data[self.root], None).unwrap().value;
-                self.free.push(self.root);
-                self.root = new_root;
-                self.size -= 1;
-
-                self.clean_tail();
-                return (true, Some(return_val));
+            // The ancestors only change if the subtree's summary did.
+            let new_data = &self.slab[new_parent];
+            if through.is_none() && new_data.height == old_height && new_data.augment == old_augment
+            {
+                return;
             }
 
-            return (true, None);
+            match self.slab[new_parent].parent() {
+                Some(n) => index = n,
+                None => {
+                    self.root = new_parent;
+                    return;
+                }
+            }
         }
-
-        (false, None)
     }
-}
-
-pub struct Iter<T> {
-    data: Vec<Option<Node<T>>>,
-    queue: VecDeque<usize>,
-}
 
-impl<T> IntoIterator for Tree<T> {
-    type Item = T;
-    type IntoIter = Iter<T>;
+    // Update a node's height to be 1 + max_height between its children,
+    // and its augment from theirs.
+    // If a node has no children, its height is calculated as 1 + -1 = 0.
+    // Returns the balance factor of the node. This is calcuated as the
+    // difference between its children's heights. If the right child has
+    // a height of 3 while the left has 1, the balance factor would be
+    // calculated as 2. This value or its inverse would require the tree
+    // to be rebalanced.
+    fn update_height(&mut self, index: usize) -> i8 {
+        let node_data = &self.slab[index];
+        let (left, right) = (node_data.left(), node_data.right());
+        let left_height = self.height(left);
+        let right_height = self.height(right);
+        let augment = A::combine(
+            left.map(|n| &self.slab[n].augment),
+            &node_data.value,
+            right.map(|n| &self.slab[n].augment),
+        );
+
+        let node_data = &mut self.slab[index];
+        node_data.height = 1 + cmp::max(left_height, right_height);
+        node_data.augment = augment;
 
-    fn into_iter(self) -> Self::IntoIter {
-        Iter {
-            data: self.data,
-            queue: VecDeque::from([self.root]),
-        }
+        right_height - left_height
     }
-}
 
-impl<T> Iterator for Iter<T> {
-    type Item = T;
+    // Returns the height of the node at INDEX, or -1 if there is no node.
+    fn height(&self, index: Option<usize>) -> i8 {
+        index.map_or(-1, |n| self.slab[n].height)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(current) = self.queue.pop_front() {
-            let current = mem::replace(&mut self.data[current], None).unwrap();
+    // Returns the difference between the heights of a node's children.
+    fn balance_factor(&self, index: usize) -> i8 {
+        let node_data = &self.slab[index];
+        self.height(node_data.right()) - self.height(node_data.left())
+    }
 
-            if let Some(n) = current.left {
-                self.queue.push_back(n);
-            }
-            if let Some(n) = current.right {
-                self.queue.push_back(n);
-            }
+    // Balance a node if one of its sides is two nodes taller than the other.
+    // In a sequence where the nodes connect A -> B -> C, a rotation is done
+    // such that node B points to its parent: A <- B -> C. If node B leans
+    // the other way: A -> B and C <- B, node A is set to point to node C
+    // and C to B: A -> C -> B, then node C is set to point to node A:
+    // A <- C -> B. Returns the new parent's index.
+    fn balance_node(&mut self, index: usize, balance_factor: i8) -> usize {
+        let node_data = &self.slab[index];
+        match balance_factor {
+            -2 => {
+                if self.balance_factor(node_data.left().unwrap()) <= 0 {
+                    self.counters.single_rotation();
+                    self.rotate_right(index)
+                } else {
+                    self.counters.double_rotation();
+                    self.rotate_left_right(index)
+                }
+            }
+            2 => {
+                if self.balance_factor(node_data.right().unwrap()) >= 0 {
+                    self.counters.single_rotation();
+                    self.rotate_left(index)
+                } else {
+                    self.counters.double_rotation();
+                    self.rotate_right_left(index)
+                }
+            }
+            _ => index,
+        }
+    }
+
+    // Rotations link the new parent to the old parent's parent.
+    fn rotate_right(&mut self, index: usize) -> usize {
+        let node_data = &self.slab[index];
+        let parent_index = node_data.parent();
+        let left_index = node_data.left().unwrap();
+        let left_right_index = self.slab[left_index].right();
+
+        self.slab[index].set_left(left_right_index);
+        if let Some(n) = left_right_index {
+            self.slab[n].set_parent(Some(index));
+        }
+
+        self.slab[left_index].set_right(Some(index));
+        self.slab[index].set_parent(Some(left_index));
+        self.replace_child(parent_index, index, Some(left_index));
+
+        self.update_height(index);
+        self.update_height(left_index);
+
+        left_index
+    }
+
+    fn rotate_left(&mut self, index: usize) -> usize {
+        let node_data = &self.slab[index];
+        let parent_index = node_data.parent();
+        let right_index = node_data.right().unwrap();
+        let right_left_index = self.slab[right_index].left();
+
+        self.slab[index].set_right(right_left_index);
+        if let Some(n) = right_left_index {
+            self.slab[n].set_parent(Some(index));
+        }
+
+        self.slab[right_index].set_left(Some(index));
+        self.slab[index].set_parent(Some(right_index));
+        self.replace_child(parent_index, index, Some(right_index));
+
+        self.update_height(index);
+        self.update_height(right_index);
+
+        right_index
+    }
+
+    fn rotate_left_right(&mut self, index: usize) -> usize {
+        self.rotate_left(self.slab[index].left().unwrap());
+        self.rotate_right(index)
+    }
+
+    fn rotate_right_left(&mut self, index: usize) -> usize {
+        self.rotate_right(self.slab[index].right().unwrap());
+        self.rotate_left(index)
+    }
+
+    // Returns the index of the smallest value in the subtree at INDEX.
+    fn min_index(&self, mut index: usize) -> usize {
+        while let Some(n) = self.slab[index].left() {
+            index = n;
+        }
+        index
+    }
+
+    // Returns the index of the largest value in the subtree at INDEX.
+    fn max_index(&self, mut index: usize) -> usize {
+        while let Some(n) = self.slab[index].right() {
+            index = n;
+        }
+        index
+    }
+
+    // Returns the index of the next larger value, if any.
+    fn successor(&self, mut index: usize) -> Option<usize> {
+        if let Some(n) = self.slab[index].right() {
+            return Some(self.min_index(n));
+        }
+        while let Some(n) = self.slab[index].parent() {
+            if self.slab[n].left() == Some(index) {
+                return Some(n);
+            }
+            index = n;
+        }
+        None
+    }
+
+    // Returns the index of the next smaller value, if any.
+    fn predecessor(&self, mut index: usize) -> Option<usize> {
+        if let Some(n) = self.slab[index].left() {
+            return Some(self.max_index(n));
+        }
+        while let Some(n) = self.slab[index].parent() {
+            if self.slab[n].right() == Some(index) {
+                return Some(n);
+            }
+            index = n;
+        }
+        None
+    }
+
+    // Unlinks the smallest value from the tree without freeing its index.
+    // Returns its index and the root of the remaining nodes, if any.
+    fn detach_min(&mut self) -> (usize, Option<usize>) {
+        let index = self.min_index(self.root);
+        let node_data = &self.slab[index];
+        let parent_index = node_data.parent();
+        let right = node_data.right();
+
+        self.replace_child(parent_index, index, right);
+        match parent_index {
+            Some(n) => {
+                self.update_and_balance(n);
+                (index, Some(self.root))
+            }
+            None => (index, right),
+        }
+    }
+
+    // Moves OTHER's nodes to the end of the slab, shifting their links.
+    // Returns the offset that was added to each of OTHER's indices.
+    fn absorb(&mut self, other: Self) -> usize {
+        self.size += other.size;
+        self.max = None;
+        self.slab.append(other.slab, |node, offset| {
+            node.set_left(node.left().map(|n| n + offset));
+            node.set_right(node.right().map(|n| n + offset));
+            node.set_parent(node.parent().map(|n| n + offset));
+        })
+    }
+
+    // Links LEFT, PIVOT and RIGHT into one balanced subtree, where every
+    // value in LEFT is smaller than PIVOT and every value in RIGHT is
+    // larger. The shorter side is attached next to a node of about the same
+    // height on the inner spine of the taller side, then the spine is
+    // rebalanced. Returns the index of the new root.
+    fn join_subtrees(&mut self, left: Option<usize>, pivot: usize, right: Option<usize>) -> usize {
+        let left_height = self.height(left);
+        let right_height = self.height(right);
+
+        let parent_index = if left_height > right_height + 1 {
+            let mut parent_index = left.unwrap();
+            let mut child = self.slab[parent_index].right();
+            while self.height(child) > right_height + 1 {
+                parent_index = child.unwrap();
+                child = self.slab[parent_index].right();
+            }
+
+            self.set_children(pivot, child, right);
+            self.slab[parent_index].set_right(Some(pivot));
+            self.root = left.unwrap();
+            parent_index
+        } else if right_height > left_height + 1 {
+            let mut parent_index = right.unwrap();
+            let mut child = self.slab[parent_index].left();
+            while self.height(child) > left_height + 1 {
+                parent_index = child.unwrap();
+                child = self.slab[parent_index].left();
+            }
+
+            self.set_children(pivot, left, child);
+            self.slab[parent_index].set_left(Some(pivot));
+            self.root = right.unwrap();
+            parent_index
+        } else {
+            self.set_children(pivot, left, right);
+            self.slab[pivot].set_parent(None);
+            self.update_height(pivot);
+            return pivot;
+        };
+
+        // The pivot is balanced, so only its ancestors need updating.
+        self.slab[pivot].set_parent(Some(parent_index));
+        self.update_height(pivot);
+        self.update_and_balance(parent_index);
+        self.root
+    }
+
+    // Frees the nodes deeper than MAX_DEPTH in the subtree at INDEX, which
+    // is at DEPTH, and pushes their values onto PRUNED in ascending order.
+    // Returns the height of what is left.
+    fn prune_subtree(
+        &mut self,
+        index: Option<usize>,
+        depth: usize,
+        max_depth: usize,
+        pruned: &mut Vec<T>,
+    ) -> i8 {
+        let Some(index) = index else {
+            return -1;
+        };
+        let node_data = &self.slab[index];
+        let (left, right) = (node_data.left(), node_data.right());
+
+        self.prune_subtree(left, depth + 1, max_depth, pruned);
+        if depth > max_depth {
+            pruned.push(self.slab.remove(index).value);
+            self.prune_subtree(right, depth + 1, max_depth, pruned);
+            return -1;
+        }
+        self.prune_subtree(right, depth + 1, max_depth, pruned);
+
+        if depth == max_depth {
+            self.set_children(index, None, None);
+        }
+        // Cutting both sides at the same depth keeps them balanced.
+        self.update_height(index);
+        self.slab[index].height
+    }
+
+    // Unlinks the node at INDEX and frees its slot, returning its value.
+    // A node with two children is replaced by the closest value on its
+    // taller side, whose node has at most one child to take its place.
+    fn remove_index(&mut self, index: usize) -> T {
+        let node_data = &self.slab[index];
+        let parent_index = node_data.parent();
+
+        let balance_index = if let (Some(left), Some(right)) = (node_data.left(), node_data.right())
+        {
+            let replace_index = if self.slab[left].height > self.slab[right].height {
+                self.max_index(left)
+            } else {
+                self.min_index(right)
+            };
+            let replace_data = &self.slab[replace_index];
+            let replace_parent = replace_data.parent().unwrap();
+            let replace_child = replace_data.left().or(replace_data.right());
+            self.replace_child(Some(replace_parent), replace_index, replace_child);
+
+            // Detaching the replacement may have changed the node's children.
+            let node_data = &self.slab[index];
+            let (left, right, height) = (node_data.left(), node_data.right(), node_data.height);
+            let augment = node_data.augment.clone();
+            self.set_children(replace_index, left, right);
+            self.slab[replace_index].height = height;
+            self.slab[replace_index].augment = augment;
+            self.replace_child(parent_index, index, Some(replace_index));
+
+            // The replacement's augment summarizes different values now,
+            // even if nothing below it changes.
+            if replace_parent == index {
+                Some((replace_index, None))
+            } else {
+                Some((replace_parent, Some(replace_index)))
+            }
+        } else {
+            let child = node_data.left().or(node_data.right());
+            self.replace_child(parent_index, index, child);
+            parent_index.map(|n| (n, None))
+        };
+
+        if let Some((n, through)) = balance_index {
+            self.update_and_balance_through(n, through);
+        }
+
+        self.size -= 1;
+        let value = self.slab.remove(index).value;
+        if self.shrink_policy == ShrinkPolicy::Aggressive {
+            self.slab.trim();
+        }
+        value
+    }
+}
+
+impl<T, I: SlotIndex> Tree<T, I> {
+    /// Returns an iterator over mutable references to the values in
+    /// ascending order. Changing a value's order relative to the others
+    /// leaves the tree in an invalid state. Augmented trees don't allow
+    /// this, since their summaries would go stale.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, I> {
+        let mut order = Vec::with_capacity(self.size);
+        let mut iter = self.iter();
+        while let Some(n) = iter.next_index() {
+            order.push(n);
+        }
+
+        IterMut {
+            values: self
+                .slab
+                .iter_mut()
+                .map(|n| n.map(|n| &mut n.value))
+                .collect(),
+            order: order.into_iter(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Like `retain`, but F may change the values it keeps. Changing a
+    /// value's order relative to the others leaves the tree in an invalid
+    /// state. Augmented trees don't allow this, like `iter_mut`.
+    pub fn retain_mut<F: FnMut(&mut T) -> bool>(&mut self, f: F) {
+        self.retain_by(f);
+    }
+
+    /// Returns mutable references to the values at every index in INDICES
+    /// at once, in the same order. Fails if an index doesn't hold a value
+    /// or appears more than once. Takes O(N²) to check the indices, so
+    /// it's meant for a few at a time. Changing a value's order relative
+    /// to the others leaves the tree in an invalid state.
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        indices: [usize; N],
+    ) -> Result<[&mut T; N], GetManyMutError> {
+        for (position, &index) in indices.iter().enumerate() {
+            if !self.is_occupied(index) {
+                return Err(GetManyMutError::Vacant(index));
+            }
+            if indices[..position].contains(&index) {
+                return Err(GetManyMutError::Duplicate(index));
+            }
+        }
+        Ok(self.slab.get_disjoint_mut(indices).map(|n| &mut n.value))
+    }
+}
+
+impl<T, I: SlotIndex, A: Augment<Reverse<T>>> Tree<Reverse<T>, I, A> {
+    /// Undoes `reversed`, returning a tree of the values in ascending
+    /// order. Each value keeps its index.
+    pub fn unreversed(self) -> Tree<T, I> {
+        self.convert_values(|Reverse(value)| value, true)
+    }
+}
+
+impl<T, I: SlotIndex> Tree<T, I, Count> {
+    /// Returns the Kth smallest value, counting from 0, or None if the
+    /// tree has K or fewer values. Takes O(log n), using the counts kept
+    /// in every node.
+    #[must_use]
+    pub fn select(&self, k: usize) -> Option<&T> {
+        self.select_index(k).map(|n| &self.slab[n].value)
+    }
+
+    fn select_index(&self, mut k: usize) -> Option<usize> {
+        if k >= self.size {
+            return None;
+        }
+
+        let mut index = self.root;
+        loop {
+            let node_data = &self.slab[index];
+            let left_len = node_data.left().map_or(0, |n| self.slab[n].augment.0);
+            index = match k.cmp(&left_len) {
+                Ordering::Less => node_data.left()?,
+                Ordering::Equal => return Some(index),
+                Ordering::Greater => {
+                    k -= left_len + 1;
+                    node_data.right()?
+                }
+            };
+        }
+    }
+
+    /// Returns the number of values in the subtree rooted at INDEX, or 0
+    /// if INDEX holds no value. Takes O(1).
+    #[must_use]
+    pub fn subtree_len(&self, index: usize) -> usize {
+        self.slab.get(index).map_or(0, |n| n.augment.0)
+    }
+
+    /// Moves every value after the K smallest into a new tree and returns
+    /// it. Splitting takes O(log n) using the counts kept in every node,
+    /// but the nodes on the smaller side are moved into a new tree, so
+    /// their indices change. When that's the side kept in this tree, its
+    /// handles stop resolving. With `ShrinkPolicy::Never` the K smallest
+    /// always keep their indices, and moving the rest takes O(n - K).
+    #[must_use]
+    pub fn split_at_rank(&mut self, k: usize) -> Self {
+        if k >= self.size {
+            let mut other = Self::new();
+            other.shrink_policy = self.shrink_policy;
+            other.duplicate_policy = self.duplicate_policy;
+            return other;
+        }
+
+        let right_is_smaller = self.size - k <= k || self.shrink_policy == ShrinkPolicy::Never;
+        let values = self.cut_at_rank(k, !right_is_smaller);
+
+        let mut other = Self::from_sorted_unchecked(values);
+        other.shrink_policy = self.shrink_policy;
+        other.duplicate_policy = self.duplicate_policy;
+        if !right_is_smaller {
+            mem::swap(self, &mut other);
+            mem::swap(&mut self.counters, &mut other.counters);
+            self.slab.supersede(&other.slab.generations());
+        }
+        other
+    }
+
+    /// Keeps only the K smallest values, like `split_at_rank` followed by
+    /// dropping the rest.
+    pub fn truncate_smallest(&mut self, k: usize) {
+        drop(self.split_at_rank(k));
+    }
+
+    /// Keeps only the K largest values, like `split_at_rank` followed by
+    /// dropping the smallest ones. With `ShrinkPolicy::Never` the largest
+    /// values keep their indices, and dropping the rest takes O(n - K).
+    pub fn truncate_largest(&mut self, k: usize) {
+        let rank = self.size.saturating_sub(k);
+        if self.shrink_policy == ShrinkPolicy::Never {
+            if rank > 0 {
+                drop(self.cut_at_rank(rank, true));
+            }
+            return;
+        }
+        // The largest values keep their slots unless they're the side that
+        // `split_at_rank` moves into a new tree.
+        let moved = self.size - rank <= rank;
+        let mut largest = self.split_at_rank(rank);
+        if moved {
+            largest.slab.supersede(&self.slab.generations());
+        }
+        mem::swap(&mut largest.counters, &mut self.counters);
+        *self = largest;
+    }
+
+    // Frees either the K smallest values or the rest, as TAKE_SMALLEST
+    // says, and returns them in ascending order. The values on the other
+    // side keep their indices.
+    fn cut_at_rank(&mut self, k: usize, take_smallest: bool) -> Vec<T> {
+        let (left, right) = self.split_rank(Some(self.root), k);
+        let (taken, kept) = match take_smallest {
+            true => (left, right),
+            false => (right, left),
+        };
+        let values = self.take_subtree(taken);
+        self.size -= values.len();
+        if let Some(n) = kept {
+            self.root = n;
+        }
+        if self.shrink_policy == ShrinkPolicy::Aggressive {
+            self.slab.trim();
+        }
+        values
+    }
+
+    // Splits the subtree at INDEX into its K smallest values and the rest,
+    // joining the pieces on the way back up. Returns both roots, which
+    // have no parents.
+    fn split_rank(&mut self, index: Option<usize>, k: usize) -> (Option<usize>, Option<usize>) {
+        let Some(n) = index else {
+            return (None, None);
+        };
+        let (left, right) = (self.slab[n].left(), self.slab[n].right());
+        for child in [left, right].into_iter().flatten() {
+            self.slab[child].set_parent(None);
+        }
+
+        let left_len = left.map_or(0, |n| self.slab[n].augment.0);
+        if k <= left_len {
+            let (smaller, larger) = self.split_rank(left, k);
+            (smaller, Some(self.join_subtrees(larger, n, right)))
+        } else {
+            let (smaller, larger) = self.split_rank(right, k - left_len - 1);
+            (Some(self.join_subtrees(left, n, smaller)), larger)
+        }
+    }
+
+    // Frees the nodes of the subtree at INDEX and returns their values in
+    // ascending order.
+    fn take_subtree(&mut self, index: Option<usize>) -> Vec<T> {
+        let mut indices = Vec::new();
+        let mut stack = Vec::new();
+        let mut current = index;
+        loop {
+            while let Some(n) = current {
+                stack.push(n);
+                current = self.slab[n].left();
+            }
+            let Some(n) = stack.pop() else {
+                break;
+            };
+            indices.push(n);
+            current = self.slab[n].right();
+        }
+
+        indices
+            .into_iter()
+            .map(|n| self.slab.remove(n).value)
+            .collect()
+    }
+
+    // Returns the number of values before the one at INDEX, counting the
+    // smaller sides on the way up.
+    fn rank_of_index(&self, mut index: usize) -> usize {
+        let count = |n: Option<usize>| n.map_or(0, |n| self.slab[n].augment.0);
+        let mut rank = count(self.slab[index].left());
+        while let Some(parent) = self.slab[index].parent() {
+            if self.slab[parent].right() == Some(index) {
+                rank += count(self.slab[parent].left()) + 1;
+            }
+            index = parent;
+        }
+        rank
+    }
+}
+
+impl<T, I: SlotIndex> Index<Rank> for Tree<T, I, Count> {
+    type Output = T;
+
+    /// Returns the value at RANK, like `select`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tree has RANK or fewer values.
+    fn index(&self, rank: Rank) -> &T {
+        match self.select(rank.0) {
+            Some(value) => value,
+            None => panic!("rank {} is out of range for length {}", rank.0, self.size),
+        }
+    }
+}
+
+impl<T: Clone, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Returns a copy of the values in ascending order.
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<T> {
+        let mut values = Vec::with_capacity(self.size);
+        values.extend(self.iter().cloned());
+        values
+    }
+
+    /// Copies the tree into `RawNode`s in pre-order, so the root comes
+    /// first and there are no vacant slots. The result can be shared with
+    /// other processes and searched with `RawNode::search`.
+    #[must_use]
+    pub fn to_raw(&self) -> Vec<RawNode<T, I>> {
+        let mut nodes = Vec::with_capacity(self.size);
+        if !self.is_empty() {
+            self.write_raw(Some(self.root), &mut nodes);
+        }
+        nodes
+    }
+
+    // Pushes the subtree at INDEX onto NODES and returns its position.
+    fn write_raw(&self, index: Option<usize>, nodes: &mut Vec<RawNode<T, I>>) -> I {
+        let Some(index) = index else {
+            return I::NONE;
+        };
+
+        let position = nodes.len();
+        let node_data = &self.slab[index];
+        nodes.push(RawNode {
+            value: node_data.value.clone(),
+            left: I::NONE,
+            right: I::NONE,
+            height: node_data.height,
+        });
+
+        nodes[position].left = self.write_raw(node_data.left(), nodes);
+        nodes[position].right = self.write_raw(node_data.right(), nodes);
+        I::from_usize(position)
+    }
+}
+
+impl<T: Hash, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Returns a hash of the tree's values. Trees holding equal values have
+    /// the same fingerprint, whatever their shape or indices, and whatever
+    /// build or machine made it. Takes O(n); a tree augmented with
+    /// `Fingerprint` keeps it up to date instead.
+    #[must_use]
+    pub fn fingerprint(&self) -> u64 {
+        self.iter()
+            .fold(0, |sum, value| sum.wrapping_add(augment::hash_value(value)))
+    }
+}
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Checks that the tree is ordered and balanced, that every node is
+    /// reachable from the root and linked to its parent, and that the
+    /// vacant slots are all on the free list.
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        let mut visited = vec![false; self.slab.len()];
+        if !self.is_empty() {
+            self.validate_subtree(Some(self.root), None, (None, None), &mut visited, 0)?;
+        }
+
+        for (index, &visited) in visited.iter().enumerate() {
+            if !visited && self.slab.get(index).is_some() {
+                return Err(InvariantError::Unreachable(index));
+            }
+        }
+        if visited.iter().filter(|&&n| n).count() != self.size {
+            return Err(InvariantError::Size);
+        }
+
+        match self.slab.find_free_list_error() {
+            Some(n) => Err(InvariantError::FreeList(n)),
+            None => Ok(()),
+        }
+    }
+
+    // Checks the subtree at INDEX, whose values must be between BOUNDS.
+    // Returns its height.
+    fn validate_subtree(
+        &self,
+        index: Option<usize>,
+        parent: Option<usize>,
+        bounds: (Option<&T>, Option<&T>),
+        visited: &mut [bool],
+        depth: i8,
+    ) -> Result<i8, InvariantError> {
+        let Some(index) = index else {
+            return Ok(-1);
+        };
+        let Some(node_data) = self.slab.get(index) else {
+            return Err(InvariantError::Vacant(index));
+        };
+        if visited[index] || node_data.parent() != parent {
+            return Err(InvariantError::Parent(index));
+        }
+        visited[index] = true;
+
+        let value = &node_data.value;
+        if bounds.0.is_some_and(|n| value < n) || bounds.1.is_some_and(|n| value > n) {
+            return Err(InvariantError::Order(index));
+        }
+        // A deeper node couldn't have a valid height.
+        let depth = depth.checked_add(1).ok_or(InvariantError::Height(index))?;
+
+        let left = (bounds.0, Some(value));
+        let right = (Some(value), bounds.1);
+        let left_height =
+            self.validate_subtree(node_data.left(), Some(index), left, visited, depth)?;
+        let right_height =
+            self.validate_subtree(node_data.right(), Some(index), right, visited, depth)?;
+
+        if (right_height - left_height).abs() > 1 {
+            return Err(InvariantError::Balance(index));
+        }
+        if node_data.height != 1 + cmp::max(left_height, right_height) {
+            return Err(InvariantError::Height(index));
+        }
+
+        let augment = A::combine(
+            node_data.left().map(|n| &self.slab[n].augment),
+            value,
+            node_data.right().map(|n| &self.slab[n].augment),
+        );
+        if node_data.augment != augment {
+            return Err(InvariantError::Augment(index));
+        }
+        Ok(node_data.height)
+    }
+
+    /// Builds a tree from the VALUES that VALIDATE accepts, sorting and
+    /// deduplicating them like `From<Vec<T>>`. Also returns the position in
+    /// VALUES and the error of every value that was rejected.
+    pub fn from_validated<E, F>(values: Vec<T>, mut validate: F) -> (Self, Vec<(usize, E)>)
+    where
+        F: FnMut(&T) -> Result<(), E>,
+    {
+        let mut errors = Vec::new();
+        let mut valid = Vec::with_capacity(values.len());
+        for (position, value) in values.into_iter().enumerate() {
+            match validate(&value) {
+                Ok(()) => valid.push(value),
+                Err(error) => errors.push((position, error)),
+            }
+        }
+
+        valid.sort();
+        valid.dedup();
+        (Self::from_sorted_unchecked(valid), errors)
+    }
+
+    /// Returns the index of VALUE if it is found.
+    pub fn contains(&self, value: T) -> Option<usize> {
+        if self.is_empty() {
+            return None;
+        }
+
+        self.search(&value).ok()
+    }
+
+    /// Returns an iterator over the values within BOUNDS in ascending
+    /// order. Finding the first takes O(log n).
+    pub fn range<R: RangeBounds<T>>(&self, bounds: R) -> impl Iterator<Item = &T> {
+        let first = self.lower_bound_index(bounds.start_bound());
+        std::iter::successors(first, |&n| self.successor(n))
+            .map(|n| &self.slab[n].value)
+            .take_while(move |&value| ordered::below_upper(value, bounds.end_bound()))
+    }
+
+    /// Returns an iterator over the values from the first one that isn't
+    /// less than START, in ascending order. Finding it takes O(log n).
+    pub fn iter_from(&self, start: &T) -> impl Iterator<Item = &T> {
+        let first = self.lower_bound_index(Bound::Included(start));
+        std::iter::successors(first, |&n| self.successor(n)).map(|n| &self.slab[n].value)
+    }
+
+    /// Returns a cursor at VALUE, or at the next larger value if VALUE
+    /// isn't in the tree, or past the ends if there is none.
+    pub fn cursor_mut_at(&mut self, value: &T) -> CursorMut<'_, T, I, A> {
+        let index = self.lower_bound_index(Bound::Included(value));
+        CursorMut::new(self, index)
+    }
+
+    /// Returns a cursor at the smallest value within the LOWER bound, or
+    /// past the ends if there is none.
+    pub fn lower_bound(&self, lower: Bound<&T>) -> Cursor<'_, T, I, A> {
+        Cursor::new(self, self.lower_bound_index(lower))
+    }
+
+    /// Returns a cursor at the largest value within the UPPER bound, or
+    /// past the ends if there is none.
+    pub fn upper_bound(&self, upper: Bound<&T>) -> Cursor<'_, T, I, A> {
+        Cursor::new(self, self.upper_bound_index(upper))
+    }
+
+    // Returns the index of the smallest value within the LOWER bound, if
+    // any.
+    fn lower_bound_index(&self, lower: Bound<&T>) -> Option<usize> {
+        let mut found = None;
+        let mut current = (!self.is_empty()).then_some(self.root);
+        while let Some(n) = current {
+            let node_data = &self.slab[n];
+            self.counters.comparison();
+            if ordered::above_lower(&node_data.value, lower) {
+                found = Some(n);
+                current = node_data.left();
+            } else {
+                current = node_data.right();
+            }
+        }
+        found
+    }
+
+    // Returns the index of the largest value within the UPPER bound, if
+    // any.
+    fn upper_bound_index(&self, upper: Bound<&T>) -> Option<usize> {
+        let mut found = None;
+        let mut current = (!self.is_empty()).then_some(self.root);
+        while let Some(n) = current {
+            let node_data = &self.slab[n];
+            self.counters.comparison();
+            if ordered::below_upper(&node_data.value, upper) {
+                found = Some(n);
+                current = node_data.right();
+            } else {
+                current = node_data.left();
+            }
+        }
+        found
+    }
+
+    /// Returns the summary of the values within BOUNDS, or None if there
+    /// are none. Takes O(log n) combines, since whole subtrees within
+    /// BOUNDS use their stored summaries.
+    pub fn query_range<R: RangeBounds<T>>(&self, bounds: R) -> Option<A> {
+        if self.is_empty() {
+            return None;
+        }
+
+        self.query_subtree(Some(self.root), bounds.start_bound(), bounds.end_bound())
+    }
+
+    // Returns the summary of the values within LOWER and UPPER in the
+    // subtree at INDEX.
+    fn query_subtree(&self, index: Option<usize>, lower: Bound<&T>, upper: Bound<&T>) -> Option<A> {
+        let node_data = &self.slab[index?];
+        if let (Bound::Unbounded, Bound::Unbounded) = (lower, upper) {
+            return Some(node_data.augment.clone());
+        }
+
+        let value = &node_data.value;
+        match (
+            ordered::above_lower(value, lower),
+            ordered::below_upper(value, upper),
+        ) {
+            (false, _) => self.query_subtree(node_data.right(), lower, upper),
+            (_, false) => self.query_subtree(node_data.left(), lower, upper),
+            // Each side now only has one bound left to check, so at most
+            // one path is followed on each.
+            (true, true) => {
+                let left = self.query_subtree(node_data.left(), lower, Bound::Unbounded);
+                let right = self.query_subtree(node_data.right(), Bound::Unbounded, upper);
+                Some(A::combine(left.as_ref(), value, right.as_ref()))
+            }
+        }
+    }
+
+    /// Returns the index of VALUE if it is found, starting the search from
+    /// the value at HINT instead of the root. The search climbs from HINT
+    /// until it reaches a subtree that would hold VALUE, so values close to
+    /// the one at HINT are found faster. A vacant HINT starts at the root.
+    pub fn binary_search_seeded(&self, hint: usize, value: &T) -> Option<usize> {
+        if self.is_empty() {
+            return None;
+        }
+
+        self.search_from(hint, value).ok()
+    }
+
+    // Descends from the root, comparing VALUE once per level. Returns the
+    // index of VALUE if it is found, otherwise the index of the node it
+    // would be attached to and which side it would go on. The tree must
+    // not be empty.
+    fn search(&self, value: &T) -> Result<usize, (usize, Ordering)> {
+        self.search_from(self.root, value)
+    }
+
+    // Compares VALUE to the value at INDEX.
+    fn compare(&self, value: &T, index: usize) -> Ordering {
+        self.counters.comparison();
+        value.cmp(&self.slab[index].value)
+    }
+
+    // Like `search`, but climbs from HINT first. A vacant HINT starts at
+    // the root.
+    fn search_from(&self, hint: usize, value: &T) -> Result<usize, (usize, Ordering)> {
+        let mut index = match self.is_occupied(hint) {
+            true => hint,
+            false => self.root,
+        };
+        let mut ordering = self.compare(value, index);
+
+        // The subtree at INDEX can only hold VALUE if VALUE is between
+        // INDEX and its parent.
+        while let Some(parent_index) = self.slab[index].parent() {
+            if ordering == Ordering::Equal {
+                break;
+            }
+
+            let parent_ordering = self.compare(value, parent_index);
+            if parent_ordering != ordering {
+                if parent_ordering == Ordering::Equal {
+                    return Ok(parent_index);
+                }
+                break;
+            }
+
+            index = parent_index;
+        }
+
+        loop {
+            let next_index = match ordering {
+                Ordering::Less => self.slab[index].left(),
+                Ordering::Greater => self.slab[index].right(),
+                Ordering::Equal => return Ok(index),
+            };
+
+            match next_index {
+                Some(n) => index = n,
+                None => return Err((index, ordering)),
+            }
+            ordering = self.compare(value, index);
+        }
+    }
+
+    // Like `search`, but first checks whether VALUE goes right next to the
+    // value at HINT, which takes at most two comparisons.
+    fn search_near(&self, hint: usize, value: &T) -> Result<usize, (usize, Ordering)> {
+        if !self.is_occupied(hint) {
+            return self.search(value);
+        }
+
+        let ordering = self.compare(value, hint);
+        let neighbor = match ordering {
+            Ordering::Less => self.predecessor(hint),
+            // Nothing follows the maximum, so appending doesn't climb.
+            Ordering::Greater if self.is_max(hint) => None,
+            Ordering::Greater => self.successor(hint),
+            Ordering::Equal => return Ok(hint),
+        };
+        if let Some(n) = neighbor {
+            match self.compare(value, n) {
+                Ordering::Equal => return Ok(n),
+                // VALUE is past the neighbor.
+                neighbor_ordering if neighbor_ordering == ordering => {
+                    return self.search_from(hint, value);
+                }
+                _ => (),
+            }
+        }
+
+        // VALUE goes between HINT and the neighbor. One of them has a free
+        // child on the side facing the other.
+        let child = match ordering {
+            Ordering::Less => self.slab[hint].left(),
+            _ => self.slab[hint].right(),
+        };
+        match (child, neighbor) {
+            (None, _) => Err((hint, ordering)),
+            (Some(_), Some(n)) => Err((n, ordering.reverse())),
+            (Some(_), None) => unreachable!(),
+        }
+    }
+
+    /// Insert VALUE into the tree. Equal values are handled according to
+    /// the duplicate policy. Returns the index that was used, or None if it
+    /// wasn't inserted.
+    pub fn insert(&mut self, value: T) -> Option<usize> {
+        match self.try_insert(value) {
+            Ok(index) => Some(index),
+            Err(error) => {
+                let (index, value) = (error.index, error.value);
+                self.insert_duplicate(index, value)
+            }
+        }
+    }
+
+    /// Inserts VALUE and returns the index that was used. If an equal
+    /// value is already in the tree, VALUE is returned in an error along
+    /// with the existing value and its index.
+    pub fn try_insert(&mut self, value: T) -> Result<usize, OccupiedError<'_, T>> {
+        if self.is_empty() {
+            return Ok(self.insert_root(value));
+        }
+
+        match self.search(&value) {
+            Ok(index) => Err(OccupiedError {
+                index,
+                existing: &self.slab[index].value,
+                value,
+            }),
+            Err((parent_index, ordering)) => Ok(self.attach(parent_index, ordering, value)),
+        }
+    }
+
+    /// Inserts VALUE like `insert`, but searches from the value at HINT
+    /// instead of the root. When VALUE goes right next to it, as when
+    /// inserting in ascending order with the last returned index as HINT,
+    /// VALUE is attached without a search.
+    pub fn insert_hint(&mut self, hint: usize, value: T) -> Option<usize> {
+        if self.is_empty() {
+            return Some(self.insert_root(value));
+        }
+
+        if self.max.is_none_or(|max| self.get_handle(max).is_none()) {
+            self.max = self.handle(self.max_index(self.root));
+        }
+
+        match self.search_near(hint, &value) {
+            Ok(index) => self.insert_duplicate(index, value),
+            Err((parent_index, ordering)) => Some(self.attach(parent_index, ordering, value)),
+        }
+    }
+
+    // Inserts VALUE, which is equal to the value at INDEX, according to
+    // the duplicate policy.
+    fn insert_duplicate(&mut self, index: usize, value: T) -> Option<usize> {
+        match self.duplicate_policy {
+            DuplicatePolicy::Reject => None,
+            DuplicatePolicy::ReplaceExisting => {
+                self.slab[index].value = value;
+                Some(index)
+            }
+            // INDEX can be any of the equal values, so search again for the
+            // place after the last one.
+            DuplicatePolicy::KeepBoth => Some(self.insert_after_equal(value)),
+        }
+    }
+
+    // Returns whether INDEX holds the cached maximum and it's still there.
+    fn is_max(&self, index: usize) -> bool {
+        self.max
+            .is_some_and(|max| max.index == index && self.get_handle(max).is_some())
+    }
+
+    fn insert_root(&mut self, value: T) -> usize {
+        self.root = self.insert_node(Node::new(value));
+        self.size = 1;
+        self.counters.height(0);
+        self.max = self.handle(self.root);
+        self.root
+    }
+
+    // Stores VALUE as the child of PARENT_INDEX on the side given by
+    // ORDERING, which must be free. Returns the index that was used.
+    fn attach(&mut self, parent_index: usize, ordering: Ordering, value: T) -> usize {
+        let insert_index = self.insert_node(Node::new(value));
+        self.link_leaf(parent_index, ordering, insert_index);
+        insert_index
+    }
+
+    // Links the new node at INSERT_INDEX as the child of PARENT_INDEX on
+    // the side given by ORDERING, which must be free.
+    fn link_leaf(&mut self, parent_index: usize, ordering: Ordering, insert_index: usize) {
+        match ordering {
+            Ordering::Less => self.slab[parent_index].set_left(Some(insert_index)),
+            Ordering::Greater => self.slab[parent_index].set_right(Some(insert_index)),
+            Ordering::Equal => unreachable!(),
+        }
+
+        // A value right of the maximum becomes the new maximum.
+        if ordering == Ordering::Greater && self.is_max(parent_index) {
+            self.max = self.handle(insert_index);
+        }
+        self.slab[insert_index].set_parent(Some(parent_index));
+        self.update_and_balance(parent_index);
+        self.size += 1;
+        self.counters.height(self.slab[self.root].height);
+    }
+
+    // Inserts VALUE after every value equal to it, whatever the duplicate
+    // policy, so equal values stay in the order they were inserted.
+    // Returns the index that was used.
+    fn insert_after_equal(&mut self, value: T) -> usize {
+        if self.is_empty() {
+            return self.insert_root(value);
+        }
+
+        let (parent_index, ordering) = self.leaf_after_equal(&value);
+        self.attach(parent_index, ordering, value)
+    }
+
+    // Puts VALUE back at INDEX, the vacant slot it was removed from,
+    // after every value equal to it. With the GENERATION the slot had
+    // before, handles to it resolve again.
+    fn restore(&mut self, index: usize, value: T, generation: u32) {
+        let position = (!self.is_empty()).then(|| self.leaf_after_equal(&value));
+        self.slab.restore(index, Node::new(value), generation);
+        match position {
+            Some((parent_index, ordering)) => self.link_leaf(parent_index, ordering, index),
+            None => {
+                self.root = index;
+                self.size = 1;
+            }
+        }
+    }
+
+    // Returns where VALUE would be attached after every value equal to it,
+    // as the parent's index and the side. The tree must not be empty.
+    fn leaf_after_equal(&self, value: &T) -> (usize, Ordering) {
+        let mut index = self.root;
+        loop {
+            let node_data = &self.slab[index];
+            let (next, ordering) = match self.compare(value, index) == Ordering::Less {
+                true => (node_data.left(), Ordering::Less),
+                false => (node_data.right(), Ordering::Greater),
+            };
+            match next {
+                Some(n) => index = n,
+                None => return (index, ordering),
+            }
+        }
+    }
+
+    /// Remove VALUE from the tree.
+    pub fn remove(&mut self, value: T) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let index = self.search(&value).ok()?;
+        Some(self.remove_index(index))
+    }
+
+    /// Removes VALUE from the tree, and returns it with the index it was
+    /// stored at.
+    pub fn remove_with_index(&mut self, value: T) -> Option<(usize, T)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let index = self.search(&value).ok()?;
+        Some((index, self.remove_index(index)))
+    }
+
+    /// Moves every value of OTHER into the tree when they are all larger
+    /// than the values already in it, returning OTHER unchanged otherwise.
+    /// Rebalancing takes O(log n), but the nodes of the smaller tree are
+    /// copied over, so their indices change. When that's this tree, its
+    /// handles stop resolving.
+    // The counters kept with the `stats` feature make a tree larger.
+    #[cfg_attr(feature = "stats", allow(clippy::result_large_err))]
+    pub fn concat_disjoint(&mut self, mut other: Self) -> Result<(), Self> {
+        if other.is_empty() {
+            return Ok(());
+        }
+        if self.is_empty() {
+            other.shrink_policy = self.shrink_policy;
+            other.duplicate_policy = self.duplicate_policy;
+            other.slab.supersede(&self.slab.generations());
+            mem::swap(&mut other.counters, &mut self.counters);
+            *self = other;
+            return Ok(());
+        }
+
+        let self_max = &self.slab[self.max_index(self.root)].value;
+        let other_min = &other.slab[other.min_index(other.root)].value;
+        if self_max >= other_min {
+            return Err(other);
+        }
+
+        let (pivot, right) = other.detach_min();
+        let (left, pivot, right) = if other.slab.len() > self.slab.len() {
+            mem::swap(self, &mut other);
+            mem::swap(&mut self.counters, &mut other.counters);
+            self.shrink_policy = other.shrink_policy;
+            self.duplicate_policy = other.duplicate_policy;
+            self.slab.supersede(&other.slab.generations());
+            let left = other.root;
+            let offset = self.absorb(other);
+            (left + offset, pivot, right)
+        } else {
+            let offset = self.absorb(other);
+            (self.root, pivot + offset, right.map(|n| n + offset))
+        };
+
+        self.root = self.join_subtrees(Some(left), pivot, right);
+        Ok(())
+    }
+
+    /// Joins LEFT, PIVOT and RIGHT into one tree in O(min(n, m) + log n),
+    /// where m is the length of the smaller tree, when every value in LEFT
+    /// is smaller than PIVOT and every value in RIGHT is larger, returning
+    /// them unchanged otherwise. Only rebalancing is logarithmic: the nodes
+    /// of the smaller tree are copied into the other's storage, so their
+    /// indices change, and when that's LEFT its handles stop resolving. The
+    /// result has LEFT's policies.
+    // Handing back all three inputs makes the error large.
+    #[allow(clippy::result_large_err)]
+    pub fn join(mut left: Self, pivot: T, mut right: Self) -> Result<Self, (Self, T, Self)> {
+        let below = left.is_empty() || left.slab[left.max_index(left.root)].value < pivot;
+        let above = right.is_empty() || pivot < right.slab[right.min_index(right.root)].value;
+        if !below || !above {
+            return Err((left, pivot, right));
+        }
+
+        let left_root = (!left.is_empty()).then_some(left.root);
+        let right_root = (!right.is_empty()).then_some(right.root);
+        let (mut tree, left_root, right_root) = if right.slab.len() > left.slab.len() {
+            right.shrink_policy = left.shrink_policy;
+            right.duplicate_policy = left.duplicate_policy;
+            mem::swap(&mut right.counters, &mut left.counters);
+            right.slab.supersede(&left.slab.generations());
+            let offset = right.absorb(left);
+            (right, left_root.map(|n| n + offset), right_root)
+        } else {
+            let offset = left.absorb(right);
+            (left, left_root, right_root.map(|n| n + offset))
+        };
+
+        let pivot = tree.insert_node(Node::new(pivot));
+        tree.size += 1;
+        tree.root = tree.join_subtrees(left_root, pivot, right_root);
+        Ok(tree)
+    }
+
+    /// Merges TREES into a new balanced tree, whose duplicate policy is
+    /// POLICY. Values equal across trees are resolved by POLICY, the first
+    /// tree being the existing one: `Reject` keeps the value from the
+    /// earliest tree, `ReplaceExisting` the one from the latest, and
+    /// `KeepBoth` keeps all of them, ordered by tree. Takes O(n log k) for
+    /// n values in k trees, as the result is built without inserting.
+    #[must_use]
+    pub fn merge_all(trees: Vec<Self>, policy: DuplicatePolicy) -> Self {
+        let total = trees.iter().map(Self::len).sum();
+        let mut streams: Vec<_> = trees
+            .into_iter()
+            .map(|tree| tree.into_sorted_vec().into_iter())
+            .collect();
+
+        // Holds the next value of every stream. Equal values pop in the
+        // order of their trees.
+        let mut heap = BinaryHeap::with_capacity(streams.len());
+        for (n, stream) in streams.iter_mut().enumerate() {
+            if let Some(value) = stream.next() {
+                heap.push(Reverse((value, n)));
+            }
+        }
+
+        let mut values: Vec<T> = Vec::with_capacity(total);
+        while let Some(Reverse((value, n))) = heap.pop() {
+            if let Some(next) = streams[n].next() {
+                heap.push(Reverse((next, n)));
+            }
+            policy.push_sorted(&mut values, value);
+        }
+
+        let mut tree = Self::from_sorted_unchecked(values);
+        tree.duplicate_policy = policy;
+        tree
+    }
+
+    /// Inserts VALUES, which should be in ascending order. When there are
+    /// many of them compared to the tree, both are merged and the tree is
+    /// rebuilt in O(n + m), which changes the indices of its values and
+    /// stops every handle from resolving. Otherwise, if VALUES aren't
+    /// sorted, or if the shrink policy is `Never`, they are inserted one by
+    /// one. Either way, equal values are handled as `insert` would.
+    pub fn extend_from_sorted<V: IntoIterator<Item = T>>(&mut self, values: V) {
+        let values: Vec<T> = values.into_iter().collect();
+        let log = (usize::BITS - self.size.leading_zeros()) as usize;
+        if values.len() * log < self.size + values.len()
+            || !values.is_sorted()
+            || self.shrink_policy == ShrinkPolicy::Never
+        {
+            for value in values {
+                self.insert(value);
+            }
+            return;
+        }
+
+        let (shrink_policy, duplicate_policy) = (self.shrink_policy, self.duplicate_policy);
+        let counters = mem::replace(&mut self.counters, Counters::new());
+        let generations = self.slab.generations();
+        let mut old = mem::replace(self, Self::new())
+            .into_sorted_vec()
+            .into_iter()
+            .peekable();
+
+        // Values already in the tree go before equal new ones.
+        let mut merged = Vec::with_capacity(old.len() + values.len());
+        for value in values {
+            while let Some(next) = old.next_if(|next| *next <= value) {
+                duplicate_policy.push_sorted(&mut merged, next);
+            }
+            duplicate_policy.push_sorted(&mut merged, value);
+        }
+        for next in old {
+            duplicate_policy.push_sorted(&mut merged, next);
+        }
+
+        *self = Self::from_sorted_unchecked(merged);
+        self.slab.supersede(&generations);
+        self.shrink_policy = shrink_policy;
+        self.duplicate_policy = duplicate_policy;
+        self.counters = counters;
+    }
+
+    /// Removes a value equal to each of KEYS, which should be in ascending
+    /// order, and returns how many were removed. When there are many keys
+    /// compared to the tree, it's walked together with them and rebuilt
+    /// from what's left in O(n + m), which changes the indices of its
+    /// values and stops every handle from resolving. Otherwise, if KEYS
+    /// aren't sorted, or if the shrink policy is `Never`, they are removed
+    /// one by one.
+    pub fn remove_all_sorted<K: IntoIterator<Item = T>>(&mut self, keys: K) -> usize {
+        let keys: Vec<T> = keys.into_iter().collect();
+        let len = self.size;
+        let log = (usize::BITS - self.size.leading_zeros()) as usize;
+        if keys.len() * log < self.size
+            || !keys.is_sorted()
+            || self.shrink_policy == ShrinkPolicy::Never
+        {
+            for key in keys {
+                self.remove(key);
+            }
+            return len - self.size;
+        }
+
+        let (shrink_policy, duplicate_policy) = (self.shrink_policy, self.duplicate_policy);
+        let counters = mem::replace(&mut self.counters, Counters::new());
+        let generations = self.slab.generations();
+        let values = mem::replace(self, Self::new()).into_sorted_vec();
+
+        let mut keys = keys.into_iter().peekable();
+        let mut kept = Vec::with_capacity(values.len());
+        for value in values {
+            while keys.next_if(|key| *key < value).is_some() {}
+            if keys.next_if(|key| *key == value).is_none() {
+                kept.push(value);
+            }
+        }
+
+        *self = Self::from_sorted_unchecked(kept);
+        self.slab.supersede(&generations);
+        self.shrink_policy = shrink_policy;
+        self.duplicate_policy = duplicate_policy;
+        self.counters = counters;
+        len - self.size
+    }
+
+    /// Inserts every value of SLICE that isn't already in the tree. SLICE
+    /// must be sorted in ascending order, otherwise some of its values may
+    /// be skipped.
+    pub fn union_with_slice(&mut self, slice: &[T])
+    where
+        T: Clone,
+    {
+        let (_, missing) = self.diff_slice(slice);
+        for position in missing {
+            self.insert(slice[position].clone());
+        }
+    }
+
+    /// Removes every value that isn't in SLICE. SLICE must be sorted in
+    /// ascending order, otherwise some values may be removed wrongly.
+    pub fn intersect_with_slice(&mut self, slice: &[T]) {
+        let (extra, _) = self.diff_slice(slice);
+        for index in extra {
+            self.remove_index(index);
+        }
+    }
+
+    // Walks the tree and SLICE together in ascending order. Returns the
+    // indices of values that are only in the tree, and the positions of
+    // values that are only in SLICE.
+    fn diff_slice(&self, slice: &[T]) -> (Vec<usize>, Vec<usize>) {
+        let mut extra = Vec::new();
+        let mut missing = Vec::new();
+        let mut iter = Iter::new(self);
+        let mut current = iter.next_index();
+        let mut position = 0;
+
+        while let Some(index) = current {
+            let ordering = match slice.get(position) {
+                Some(value) => self.slab[index].value.cmp(value),
+                None => Ordering::Less,
+            };
+
+            match ordering {
+                Ordering::Less => {
+                    extra.push(index);
+                    current = iter.next_index();
+                }
+                Ordering::Greater => {
+                    missing.push(position);
+                    position += 1;
+                }
+                Ordering::Equal => {
+                    current = iter.next_index();
+                    position += 1;
+                }
+            }
+        }
+
+        missing.extend(position..slice.len());
+        (extra, missing)
+    }
+}
+
+/// A `Vec` whose values are claimed to be sorted in ascending order with no
+/// duplicates. Converting it into a `Tree` checks the claim instead of sorting.
+#[derive(Clone, Debug)]
+pub struct SortedVec<T>(pub Vec<T>);
+
+/// Returned when a `SortedVec` is not strictly ascending.
+#[derive(Clone, Debug)]
+pub struct UnsortedError<T> {
+    index: usize,
+    values: Vec<T>,
+}
+
+impl<T> UnsortedError<T> {
+    /// Returns the index of the first value that is not greater than the
+    /// value before it.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the rejected values.
+    #[must_use]
+    pub fn into_inner(self) -> Vec<T> {
+        self.values
+    }
+}
+
+impl<T> fmt::Display for UnsortedError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "value at index {} is out of order", self.index)
+    }
+}
+
+impl<T: fmt::Debug> Error for UnsortedError<T> {}
+
+/// Returned by `Tree::try_insert` when an equal value is already in the
+/// tree.
+#[derive(Debug)]
+pub struct OccupiedError<'a, T> {
+    index: usize,
+    existing: &'a T,
+    value: T,
+}
+
+impl<'a, T> OccupiedError<'a, T> {
+    /// Returns the index of the existing value.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the value that is already in the tree.
+    #[must_use]
+    pub const fn existing(&self) -> &'a T {
+        self.existing
+    }
+
+    /// Returns the value that wasn't inserted.
+    #[must_use]
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+impl<T> fmt::Display for OccupiedError<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an equal value is already at index {}", self.index)
+    }
+}
+
+impl<T: fmt::Debug> Error for OccupiedError<'_, T> {}
+
+/// Returned by `Tree::validate` with the index of the slot where an
+/// invariant is broken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvariantError {
+    /// The value is not between the values of its ancestors.
+    Order(usize),
+    /// The node's stored height is wrong.
+    Height(usize),
+    /// The heights of the node's children differ by more than one.
+    Balance(usize),
+    /// The node's stored augment doesn't match its subtree.
+    Augment(usize),
+    /// The node doesn't link back to its parent, or is linked twice.
+    Parent(usize),
+    /// A link points to a slot with no node.
+    Vacant(usize),
+    /// The slot holds a node that can't be reached from the root.
+    Unreachable(usize),
+    /// The number of reachable nodes doesn't match the tree's length.
+    Size,
+    /// The vacant slot is linked wrongly or missing from the free list.
+    FreeList(usize),
+}
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Order(n) => write!(f, "value at index {n} is out of order"),
+            Self::Height(n) => write!(f, "node at index {n} has the wrong height"),
+            Self::Balance(n) => write!(f, "node at index {n} is unbalanced"),
+            Self::Augment(n) => write!(f, "node at index {n} has the wrong augment"),
+            Self::Parent(n) => write!(f, "node at index {n} has the wrong parent"),
+            Self::Vacant(n) => write!(f, "link to vacant index {n}"),
+            Self::Unreachable(n) => write!(f, "node at index {n} is unreachable"),
+            Self::Size => write!(f, "length doesn't match the number of nodes"),
+            Self::FreeList(n) => write!(f, "free list is broken at index {n}"),
+        }
+    }
+}
+
+impl Error for InvariantError {}
+
+/// The error returned by `Tree::get_many_mut`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GetManyMutError {
+    /// The index doesn't hold a value.
+    Vacant(usize),
+    /// The index was given more than once.
+    Duplicate(usize),
+}
+
+impl fmt::Display for GetManyMutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vacant(n) => write!(f, "index {n} doesn't hold a value"),
+            Self::Duplicate(n) => write!(f, "index {n} was given more than once"),
+        }
+    }
+}
+
+impl Error for GetManyMutError {}
+
+/// The first place where `Tree::validate_against` found the tree and the
+/// expected values to disagree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Divergence<'a, T> {
+    /// The value at INDEX is not the expected one.
+    Value {
+        index: usize,
+        expected: T,
+        found: &'a T,
+    },
+    /// The tree ends at INDEX, but more values were expected.
+    Missing { index: usize, expected: T },
+    /// The expected values end at INDEX, but the tree has more.
+    Extra { index: usize, found: &'a T },
+}
+
+impl<T> Divergence<'_, T> {
+    /// Returns the position in ascending order where the divergence is.
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        match self {
+            Self::Value { index, .. } | Self::Missing { index, .. } | Self::Extra { index, .. } => {
+                *index
+            }
+        }
+    }
+}
+
+impl<T> fmt::Display for Divergence<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value { index, .. } => write!(f, "value at index {index} differs"),
+            Self::Missing { index, .. } => write!(f, "tree ends early at index {index}"),
+            Self::Extra { index, .. } => write!(f, "tree has extra values from index {index}"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> Error for Divergence<'_, T> {}
+
+impl<T: Ord> From<Vec<T>> for Tree<T> {
+    /// Sorts and deduplicates VALUES, then builds a balanced tree. When
+    /// values are equal, the first one is kept.
+    fn from(mut values: Vec<T>) -> Self {
+        values.sort();
+        values.dedup();
+        Self::from_sorted_unchecked(values)
+    }
+}
+
+impl<T: Ord, const N: usize> From<[T; N]> for Tree<T> {
+    fn from(values: [T; N]) -> Self {
+        Self::from(Vec::from(values))
+    }
+}
+
+impl<T: Ord> TryFrom<SortedVec<T>> for Tree<T> {
+    type Error = UnsortedError<T>;
+
+    fn try_from(values: SortedVec<T>) -> Result<Self, Self::Error> {
+        let values = values.0;
+
+        match values.windows(2).position(|w| w[0] >= w[1]) {
+            Some(n) => Err(UnsortedError {
+                index: n + 1,
+                values,
+            }),
+            None => Ok(Self::from_sorted_unchecked(values)),
+        }
+    }
+}
+
+pub struct IntoIter<T, I = u32, A = ()> {
+    slab: Slab<Node<T, I, A>, I>,
+    queue: VecDeque<usize>,
+}
+
+impl<T, I: SlotIndex, A: Augment<T>> IntoIterator for Tree<T, I, A> {
+    type Item = T;
+    type IntoIter = IntoIter<T, I, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut queue = VecDeque::new();
+        if !self.is_empty() {
+            queue.push_back(self.root);
+        }
+
+        IntoIter {
+            slab: self.slab,
+            queue,
+        }
+    }
+}
+
+impl<T, I: SlotIndex, A> Iterator for IntoIter<T, I, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(current) = self.queue.pop_front() {
+            let current = self.slab.remove(current);
+
+            if let Some(n) = current.left() {
+                self.queue.push_back(n);
+            }
+            if let Some(n) = current.right() {
+                self.queue.push_back(n);
+            }
 
             Some(current.value)
         } else {
@@ -490,3 +2517,94 @@ impl<T> Iterator for Iter<T> {
         }
     }
 }
+
+pub struct Iter<'a, T, I = u32, A = ()> {
+    slab: &'a Slab<Node<T, I, A>, I>,
+    stack: Vec<usize>,
+    remaining: usize,
+}
+
+impl<'a, T, I: SlotIndex, A> Iter<'a, T, I, A> {
+    fn new(tree: &'a Tree<T, I, A>) -> Self {
+        let mut iter = Self {
+            slab: &tree.slab,
+            stack: Vec::new(),
+            remaining: tree.size,
+        };
+
+        if tree.size != 0 {
+            iter.push_left(Some(tree.root));
+        }
+        iter
+    }
+
+    // Pushes INDEX and all of its left descendants, leaving the
+    // smallest value of the subtree on top of the stack.
+    fn push_left(&mut self, mut index: Option<usize>) {
+        while let Some(n) = index {
+            self.stack.push(n);
+            index = self.slab[n].left();
+        }
+    }
+
+    fn next_index(&mut self) -> Option<usize> {
+        let current = self.stack.pop()?;
+        self.push_left(self.slab[current].right());
+        self.remaining -= 1;
+
+        Some(current)
+    }
+}
+
+impl<'a, T, I: SlotIndex, A> Iterator for Iter<'a, T, I, A> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_index().map(|n| &self.slab[n].value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, I: SlotIndex, A> ExactSizeIterator for Iter<'_, T, I, A> {}
+
+impl<'a, T, I: SlotIndex, A: Augment<T>> IntoIterator for &'a Tree<T, I, A> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, I, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct IterMut<'a, T, I = u32> {
+    // Indexed like the tree's slab, values are taken out in ORDER.
+    values: Vec<Option<&'a mut T>>,
+    order: vec::IntoIter<usize>,
+    marker: PhantomData<I>,
+}
+
+impl<'a, T, I> Iterator for IterMut<'a, T, I> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.order.next().and_then(|n| self.values[n].take())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.order.size_hint()
+    }
+}
+
+impl<T, I> ExactSizeIterator for IterMut<'_, T, I> {}
+
+impl<'a, T, I: SlotIndex> IntoIterator for &'a mut Tree<T, I> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T, I>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
@@ -0,0 +1,155 @@
+use std::cmp;
+use std::hash::{Hash, Hasher};
+use std::ops::Add;
+
+/// A summary of a subtree's values that every node keeps up to date as the
+/// tree changes. It must only depend on the values and their order, not on
+/// how the subtree is shaped, since rotations regroup nodes. In other words,
+/// combining must be associative, like a monoid.
+pub trait Augment<T>: Clone + PartialEq {
+    /// Returns the summary of a subtree from the summaries of its left and
+    /// right subtrees, if it has them, and the VALUE at its root.
+    fn combine(left: Option<&Self>, value: &T, right: Option<&Self>) -> Self;
+}
+
+impl<T> Augment<T> for () {
+    fn combine(_: Option<&Self>, _: &T, _: Option<&Self>) -> Self {}
+}
+
+/// The number of values in a subtree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Count(pub usize);
+
+impl<T> Augment<T> for Count {
+    fn combine(left: Option<&Self>, _: &T, right: Option<&Self>) -> Self {
+        let count = |side: Option<&Self>| side.map_or(0, |n| n.0);
+        Self(count(left) + 1 + count(right))
+    }
+}
+
+/// The sum of a subtree's values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sum<T>(pub T);
+
+impl<T: Clone + PartialEq + Add<Output = T>> Augment<T> for Sum<T> {
+    fn combine(left: Option<&Self>, value: &T, right: Option<&Self>) -> Self {
+        let mut sum = value.clone();
+        if let Some(n) = left {
+            sum = n.0.clone() + sum;
+        }
+        if let Some(n) = right {
+            sum = sum + n.0.clone();
+        }
+        Self(sum)
+    }
+}
+
+/// The smallest of a subtree's values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Min<T>(pub T);
+
+impl<T: Clone + Ord> Augment<T> for Min<T> {
+    fn combine(left: Option<&Self>, value: &T, right: Option<&Self>) -> Self {
+        let min = [left, right]
+            .into_iter()
+            .flatten()
+            .fold(value, |min, n| cmp::min(min, &n.0));
+        Self(min.clone())
+    }
+}
+
+/// The largest of a subtree's values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Max<T>(pub T);
+
+impl<T: Clone + Ord> Augment<T> for Max<T> {
+    fn combine(left: Option<&Self>, value: &T, right: Option<&Self>) -> Self {
+        let max = [left, right]
+            .into_iter()
+            .flatten()
+            .fold(value, |max, n| cmp::max(max, &n.0));
+        Self(max.clone())
+    }
+}
+
+/// A hash of a subtree's values, equal to `Tree::fingerprint` for the whole
+/// tree. Augmenting a tree with it keeps the fingerprint up to date as
+/// values come and go, so `query_range(..)` returns it in O(1).
+/// Fingerprints use a fixed hash, so they can be stored and compared with
+/// ones made by other builds, as long as the values hash the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint(pub u64);
+
+impl<T: Hash> Augment<T> for Fingerprint {
+    fn combine(left: Option<&Self>, value: &T, right: Option<&Self>) -> Self {
+        let sum = [left, right]
+            .into_iter()
+            .flatten()
+            .fold(hash_value(value), |sum, n| sum.wrapping_add(n.0));
+        Self(sum)
+    }
+}
+
+// Hashes a single value for a fingerprint. Fingerprints sum these, which
+// makes them independent of the order values are visited in.
+pub(crate) fn hash_value<T: Hash>(value: &T) -> u64 {
+    let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// 64-bit FNV-1a, which unlike `DefaultHasher` is fixed, so fingerprints
+// can be compared between builds and machines. Integers are written in
+// little-endian order, and sizes as 64 bits, to keep them the same on
+// every platform.
+struct Fnv1a(u64);
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+}
+
+// Tuples keep several summaries at once, each combined on its own.
+macro_rules! impl_augment_tuple {
+    ($($a:ident $n:tt),*) => {
+        impl<T, $($a: Augment<T>),*> Augment<T> for ($($a,)*) {
+            fn combine(left: Option<&Self>, value: &T, right: Option<&Self>) -> Self {
+                ($($a::combine(left.map(|n| &n.$n), value, right.map(|n| &n.$n)),)*)
+            }
+        }
+    };
+}
+
+impl_augment_tuple!(A 0, B 1);
+impl_augment_tuple!(A 0, B 1, C 2);
+impl_augment_tuple!(A 0, B 1, C 2, D 3);
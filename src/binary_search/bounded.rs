@@ -0,0 +1,157 @@
+//! A tree that holds at most a fixed number of values, built on `Tree`.
+
+use super::Tree;
+
+/// Decides which value a `BoundedTree` gives up when a new one would take
+/// it past its capacity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Eviction {
+    /// The smallest value is evicted, which may be the new one, so the
+    /// largest values are kept.
+    #[default]
+    DropSmallest,
+    /// The largest value is evicted, which may be the new one, so the
+    /// smallest values are kept.
+    DropLargest,
+    /// The new value is evicted and the tree is left as it is.
+    Reject,
+}
+
+/// A set with a capacity, such as the top N scores seen so far. Inserting
+/// past the capacity evicts a value chosen by its `Eviction`, and passes
+/// it to the eviction callback. Every operation takes O(log n).
+pub struct BoundedTree<T> {
+    tree: Tree<T>,
+    capacity: usize,
+    eviction: Eviction,
+    on_evict: Option<Box<dyn FnMut(T)>>,
+}
+
+impl<T> BoundedTree<T> {
+    /// Creates an empty tree that holds at most CAPACITY values, evicting
+    /// them as EVICTION says.
+    #[must_use]
+    pub const fn new(capacity: usize, eviction: Eviction) -> Self {
+        Self {
+            tree: Tree::new(),
+            capacity,
+            eviction,
+            on_evict: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.tree.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.tree.size == 0
+    }
+
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[must_use]
+    pub const fn eviction(&self) -> Eviction {
+        self.eviction
+    }
+
+    /// Calls ON_EVICT with every value evicted to stay within capacity,
+    /// instead of dropping it. Values that are removed or popped aren't
+    /// passed to it.
+    pub fn set_on_evict<F: FnMut(T) + 'static>(&mut self, on_evict: F) {
+        self.on_evict = Some(Box::new(on_evict));
+    }
+
+    /// Returns the smallest value.
+    #[must_use]
+    pub fn first(&self) -> Option<&T> {
+        self.first_index().map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Returns the largest value.
+    #[must_use]
+    pub fn last(&self) -> Option<&T> {
+        self.last_index().map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Removes the smallest value and returns it.
+    pub fn pop_first(&mut self) -> Option<T> {
+        let index = self.first_index()?;
+        Some(self.tree.remove_index(index))
+    }
+
+    /// Removes the largest value and returns it.
+    pub fn pop_last(&mut self) -> Option<T> {
+        let index = self.last_index()?;
+        Some(self.tree.remove_index(index))
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.tree.iter()
+    }
+
+    fn first_index(&self) -> Option<usize> {
+        (!self.is_empty()).then(|| self.tree.min_index(self.tree.root))
+    }
+
+    fn last_index(&self) -> Option<usize> {
+        (!self.is_empty()).then(|| self.tree.max_index(self.tree.root))
+    }
+
+    fn evict(&mut self, value: T) {
+        if let Some(on_evict) = &mut self.on_evict {
+            on_evict(value);
+        }
+    }
+}
+
+impl<T: Ord> BoundedTree<T> {
+    /// Returns whether VALUE is in the tree.
+    #[must_use]
+    pub fn contains(&self, value: &T) -> bool {
+        !self.is_empty() && self.tree.search(value).is_ok()
+    }
+
+    /// Inserts VALUE, evicting a value if the tree was full. Returns
+    /// whether VALUE is in the tree afterwards, which is false if it was
+    /// already there, or was evicted itself.
+    pub fn insert(&mut self, value: T) -> bool {
+        if self.contains(&value) {
+            return false;
+        }
+        if self.len() >= self.capacity && self.eviction == Eviction::Reject {
+            self.evict(value);
+            return false;
+        }
+
+        let Some(index) = self.tree.insert(value) else {
+            unreachable!()
+        };
+        let evicted = match self.len() > self.capacity {
+            false => None,
+            true => match self.eviction {
+                Eviction::DropSmallest => self.first_index(),
+                Eviction::DropLargest => self.last_index(),
+                Eviction::Reject => unreachable!(),
+            },
+        };
+
+        let Some(evicted) = evicted else {
+            return true;
+        };
+        let value = self.tree.remove_index(evicted);
+        self.evict(value);
+        evicted != index
+    }
+
+    /// Removes VALUE and returns it, if it was in the tree.
+    pub fn remove(&mut self, value: T) -> Option<T> {
+        self.tree.remove(value)
+    }
+}
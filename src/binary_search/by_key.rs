@@ -0,0 +1,133 @@
+//! A set of values ordered by a key taken from each one, built on `Tree`.
+
+use super::Tree;
+use std::cmp::Ordering;
+use std::mem;
+
+// A value and the key it had when it was inserted.
+struct Keyed<K, T> {
+    key: K,
+    value: T,
+}
+
+impl<K: Ord, T> PartialEq for Keyed<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Ord, T> Eq for Keyed<K, T> {}
+
+impl<K: Ord, T> PartialOrd for Keyed<K, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, T> Ord for Keyed<K, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// A set of values ordered by the key F returns for each, such as one of
+/// a struct's fields, and looked up by that key. No two values have
+/// equal keys. Every key is taken once, on insertion, and kept next to
+/// its value. Every operation takes O(log n).
+pub struct TreeByKey<T, K, F: Fn(&T) -> K> {
+    tree: Tree<Keyed<K, T>>,
+    key: F,
+}
+
+impl<T, K, F: Fn(&T) -> K> TreeByKey<T, K, F> {
+    /// Creates an empty set ordering values by the key KEY returns.
+    #[must_use]
+    pub const fn new(key: F) -> Self {
+        Self {
+            tree: Tree::new(),
+            key,
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.tree.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.tree.size == 0
+    }
+
+    /// Returns an iterator over the values in ascending order of key.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.tree.iter().map(|keyed| &keyed.value)
+    }
+}
+
+impl<T, K: Ord, F: Fn(&T) -> K> TreeByKey<T, K, F> {
+    /// Returns the value whose key is KEY, if any.
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        let index = self.find(key)?;
+        Some(&self.tree.slab[index].value.value)
+    }
+
+    /// Returns whether a value's key is KEY.
+    #[must_use]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Inserts VALUE. Returns the value with the same key it replaced, if
+    /// any.
+    pub fn insert(&mut self, value: T) -> Option<T> {
+        let key = (self.key)(&value);
+        if self.is_empty() {
+            self.tree.insert_root(Keyed { key, value });
+            return None;
+        }
+
+        match self.find_slot(&key) {
+            Ok(index) => Some(mem::replace(&mut self.tree.slab[index].value.value, value)),
+            Err((parent_index, ordering)) => {
+                self.tree
+                    .attach(parent_index, ordering, Keyed { key, value });
+                None
+            }
+        }
+    }
+
+    /// Removes the value whose key is KEY and returns it, if any.
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let index = self.find(key)?;
+        Some(self.tree.remove_index(index).value)
+    }
+
+    fn find(&self, key: &K) -> Option<usize> {
+        match self.is_empty() {
+            true => None,
+            false => self.find_slot(key).ok(),
+        }
+    }
+
+    // Returns the index of the value whose key is KEY, or else the index
+    // of the leaf it would be attached to and the side, like
+    // `Tree::search`. The set must not be empty.
+    fn find_slot(&self, key: &K) -> Result<usize, (usize, Ordering)> {
+        let mut index = self.tree.root;
+        loop {
+            let node = &self.tree.slab[index];
+            let ordering = key.cmp(&node.value.key);
+            let next = match ordering {
+                Ordering::Less => node.left(),
+                Ordering::Greater => node.right(),
+                Ordering::Equal => return Ok(index),
+            };
+            match next {
+                Some(n) => index = n,
+                None => return Err((index, ordering)),
+            }
+        }
+    }
+}
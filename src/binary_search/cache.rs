@@ -0,0 +1,328 @@
+//! Caches that evict entries in order of a score, built on two `Tree`s.
+
+use super::Tree;
+use std::cmp::Ordering;
+use std::mem;
+
+// An entry in the tree ordered by key. ORDER is the index of its entry
+// in the tree ordered by score.
+struct Keyed<K, V> {
+    key: K,
+    value: V,
+    order: usize,
+}
+
+impl<K: Ord, V> PartialEq for Keyed<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Ord, V> Eq for Keyed<K, V> {}
+
+impl<K: Ord, V> PartialOrd for Keyed<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for Keyed<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+// An entry in the tree ordered by score, then by when it was scored so
+// that no two are equal. KEYED is the index of its entry in the tree
+// ordered by key.
+struct Scored<S> {
+    score: S,
+    seq: u64,
+    keyed: usize,
+}
+
+impl<S: Ord> PartialEq for Scored<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<S: Ord> Eq for Scored<S> {}
+
+impl<S: Ord> PartialOrd for Scored<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: Ord> Ord for Scored<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.score, self.seq).cmp(&(&other.score, other.seq))
+    }
+}
+
+/// A map with a capacity, where every entry has a score, such as a
+/// priority or an expiry time. Inserting past the capacity evicts the
+/// entry with the lowest score, the oldest among equal ones, and passes
+/// it to the eviction callback. Every operation takes O(log n).
+///
+/// Entries are kept in two trees, one ordered by key and one by score,
+/// which link to each other by index. Indices in a `Tree` never move, so
+/// the links stay valid as entries come and go.
+pub struct Cache<K, V, S = u64> {
+    by_key: Tree<Keyed<K, V>>,
+    by_score: Tree<Scored<S>>,
+    capacity: usize,
+    next_seq: u64,
+    on_evict: Option<Box<dyn FnMut(K, V)>>,
+}
+
+impl<K, V, S> Cache<K, V, S> {
+    /// Creates an empty cache that holds at most CAPACITY entries.
+    #[must_use]
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            by_key: Tree::new(),
+            by_score: Tree::new(),
+            capacity,
+            next_seq: 0,
+            on_evict: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.by_key.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.by_key.size == 0
+    }
+
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Calls ON_EVICT with every entry evicted to stay within capacity,
+    /// instead of dropping it. Entries that are removed or popped aren't
+    /// passed to it.
+    pub fn set_on_evict<F: FnMut(K, V) + 'static>(&mut self, on_evict: F) {
+        self.on_evict = Some(Box::new(on_evict));
+    }
+}
+
+impl<K: Ord, V, S: Ord> Cache<K, V, S> {
+    /// Returns the value KEY maps to, if any.
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let index = self.find(key)?;
+        Some(&self.by_key.slab[index].value.value)
+    }
+
+    /// Returns a mutable reference to the value KEY maps to, if any.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let index = self.find(key)?;
+        Some(&mut self.by_key.slab[index].value.value)
+    }
+
+    /// Returns the score of KEY's entry, if any.
+    #[must_use]
+    pub fn score(&self, key: &K) -> Option<&S> {
+        let order = self.by_key.slab[self.find(key)?].value.order;
+        Some(&self.by_score.slab[order].value.score)
+    }
+
+    /// Returns the entry with the lowest score, the next to be evicted.
+    #[must_use]
+    pub fn peek_lowest(&self) -> Option<(&K, &V, &S)> {
+        let order = self.lowest()?;
+        let scored = &self.by_score.slab[order].value;
+        let keyed = &self.by_key.slab[scored.keyed].value;
+        Some((&keyed.key, &keyed.value, &scored.score))
+    }
+
+    /// Maps KEY to VALUE with SCORE. Returns the value KEY mapped to
+    /// before, if any. A new entry past the capacity evicts the entry
+    /// with the lowest score, which may be the new one.
+    pub fn insert(&mut self, key: K, value: V, score: S) -> Option<V> {
+        if let Some(index) = self.find(&key) {
+            let old = mem::replace(&mut self.by_key.slab[index].value.value, value);
+            self.rescore(index, score);
+            return Some(old);
+        }
+
+        // The entries link to each other, so the first one inserted is
+        // linked once the second one has an index.
+        let order = self.insert_scored(score, 0);
+        let keyed = Keyed { key, value, order };
+        let Some(index) = self.by_key.insert(keyed) else {
+            unreachable!()
+        };
+        self.by_score.slab[order].value.keyed = index;
+
+        while self.len() > self.capacity {
+            let Some((key, value, _)) = self.pop_lowest() else {
+                unreachable!()
+            };
+            if let Some(on_evict) = &mut self.on_evict {
+                on_evict(key, value);
+            }
+        }
+        None
+    }
+
+    /// Changes the score of KEY's entry to SCORE. Returns false if KEY
+    /// isn't in the cache.
+    pub fn set_score(&mut self, key: &K, score: S) -> bool {
+        let Some(index) = self.find(key) else {
+            return false;
+        };
+        self.rescore(index, score);
+        true
+    }
+
+    /// Removes KEY's entry and returns its value, if any.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let index = self.find(key)?;
+        let order = self.by_key.slab[index].value.order;
+        self.by_score.remove_index(order);
+        Some(self.by_key.remove_index(index).value)
+    }
+
+    /// Removes the entry with the lowest score and returns it.
+    pub fn pop_lowest(&mut self) -> Option<(K, V, S)> {
+        let order = self.lowest()?;
+        let scored = self.by_score.remove_index(order);
+        let keyed = self.by_key.remove_index(scored.keyed);
+        Some((keyed.key, keyed.value, scored.score))
+    }
+
+    /// Removes the entry with the lowest score if it's at most NOW, as
+    /// when scores are expiry times, and returns it.
+    pub fn pop_expired(&mut self, now: &S) -> Option<(K, V, S)> {
+        let order = self.lowest()?;
+        if self.by_score.slab[order].value.score > *now {
+            return None;
+        }
+        self.pop_lowest()
+    }
+
+    fn find(&self, key: &K) -> Option<usize> {
+        let mut current = (!self.is_empty()).then_some(self.by_key.root);
+        while let Some(n) = current {
+            let node = &self.by_key.slab[n];
+            current = match key.cmp(&node.value.key) {
+                Ordering::Less => node.left(),
+                Ordering::Greater => node.right(),
+                Ordering::Equal => return Some(n),
+            };
+        }
+        None
+    }
+
+    fn lowest(&self) -> Option<usize> {
+        (!self.is_empty()).then(|| self.by_score.min_index(self.by_score.root))
+    }
+
+    // Inserts SCORE for the entry at KEYED. Returns the index that was
+    // used.
+    fn insert_scored(&mut self, score: S, keyed: usize) -> usize {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let Some(index) = self.by_score.insert(Scored { score, seq, keyed }) else {
+            unreachable!()
+        };
+        index
+    }
+
+    // Moves the entry at INDEX in the tree ordered by key to SCORE.
+    fn rescore(&mut self, index: usize, score: S) {
+        let order = self.by_key.slab[index].value.order;
+        self.by_score.remove_index(order);
+        self.by_key.slab[index].value.order = self.insert_scored(score, index);
+    }
+}
+
+/// A cache that evicts the least recently used entry once it's past its
+/// capacity. Getting or inserting an entry makes it the most recently
+/// used. Every operation takes O(log n).
+pub struct LruCache<K, V> {
+    // Scores are the time of last use on a clock that ticks every use.
+    cache: Cache<K, V, u64>,
+    clock: u64,
+}
+
+impl<K, V> LruCache<K, V> {
+    /// Creates an empty cache that holds at most CAPACITY entries.
+    #[must_use]
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            cache: Cache::new(capacity),
+            clock: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.cache.capacity()
+    }
+
+    /// Calls ON_EVICT with every entry evicted to stay within capacity,
+    /// instead of dropping it.
+    pub fn set_on_evict<F: FnMut(K, V) + 'static>(&mut self, on_evict: F) {
+        self.cache.set_on_evict(on_evict);
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+impl<K: Ord, V> LruCache<K, V> {
+    /// Returns the value KEY maps to, if any, and makes it the most
+    /// recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let now = self.tick();
+        if !self.cache.set_score(key, now) {
+            return None;
+        }
+        self.cache.get(key)
+    }
+
+    /// Returns the value KEY maps to, if any, without counting as a use.
+    #[must_use]
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.cache.get(key)
+    }
+
+    /// Maps KEY to VALUE as the most recently used entry. Returns the
+    /// value KEY mapped to before, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let now = self.tick();
+        self.cache.insert(key, value, now)
+    }
+
+    /// Removes KEY's entry and returns its value, if any.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.cache.remove(key)
+    }
+
+    /// Removes the least recently used entry and returns it.
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let (key, value, _) = self.cache.pop_lowest()?;
+        Some((key, value))
+    }
+}
@@ -0,0 +1,117 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// An order on values of T given by a type rather than by T itself, so
+/// the same type can be ordered differently in different trees, or be
+/// ordered at all. Trees hold such values as `Compared<T, C>`.
+pub trait Compare<T: ?Sized> {
+    /// Returns how A compares to B. It must be a total order, like
+    /// `Ord::cmp`.
+    fn compare(a: &T, b: &T) -> Ordering;
+}
+
+/// The order of T's own `Ord` impl.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Natural;
+
+impl<T: Ord + ?Sized> Compare<T> for Natural {
+    fn compare(a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// The total order of floats given by `total_cmp`, in which -0.0 comes
+/// before 0.0, and NaNs come after infinity, or before negative infinity
+/// if their sign is set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TotalOrder;
+
+impl Compare<f32> for TotalOrder {
+    fn compare(a: &f32, b: &f32) -> Ordering {
+        a.total_cmp(b)
+    }
+}
+
+impl Compare<f64> for TotalOrder {
+    fn compare(a: &f64, b: &f64) -> Ordering {
+        a.total_cmp(b)
+    }
+}
+
+/// An `f32` that can be stored in a tree, ordered by `TotalOrder`.
+pub type TotalF32 = Compared<f32, TotalOrder>;
+
+/// An `f64` that can be stored in a tree, ordered by `TotalOrder`.
+pub type TotalF64 = Compared<f64, TotalOrder>;
+
+/// A value ordered by C rather than by its own `Ord` impl, if it has
+/// one. Derefs to the value.
+pub struct Compared<T, C = Natural> {
+    pub value: T,
+    order: PhantomData<C>,
+}
+
+impl<T, C> Compared<T, C> {
+    #[must_use]
+    pub const fn new(value: T) -> Self {
+        Self {
+            value,
+            order: PhantomData,
+        }
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, C> From<T> for Compared<T, C> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T, C> Deref for Compared<T, C> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Clone, C> Clone for Compared<T, C> {
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone())
+    }
+}
+
+impl<T: Copy, C> Copy for Compared<T, C> {}
+
+impl<T: fmt::Debug, C> fmt::Debug for Compared<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T, C: Compare<T>> PartialEq for Compared<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        C::compare(&self.value, &other.value) == Ordering::Equal
+    }
+}
+
+impl<T, C: Compare<T>> Eq for Compared<T, C> {}
+
+impl<T, C: Compare<T>> PartialOrd for Compared<T, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, C: Compare<T>> Ord for Compared<T, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        C::compare(&self.value, &other.value)
+    }
+}
@@ -0,0 +1,107 @@
+use super::persistent;
+use std::mem;
+use std::ops::RangeBounds;
+use std::sync::{Mutex, PoisonError, RwLock};
+
+/// A set that many threads can read while one thread at a time writes.
+/// Readers take the current version of a `persistent::Tree` and search it
+/// without holding any lock. A writer builds the next version on the side
+/// and only locks readers out while swapping it in, which is O(1).
+///
+/// A panic in `Ord` during a write leaves the current version in place,
+/// so the tree never ends up half changed, and poisoned locks are ignored.
+pub struct Tree<T> {
+    current: RwLock<persistent::Tree<T>>,
+    // Held for the whole of a write, so that writes don't overwrite
+    // each other's versions.
+    writer: Mutex<()>,
+}
+
+impl<T> Tree<T> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            current: RwLock::new(persistent::Tree::new()),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Returns the current version. It stays the same while the tree
+    /// keeps changing, so several reads can be made from one state.
+    #[must_use]
+    pub fn load(&self) -> persistent::Tree<T> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.load().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.load().is_empty()
+    }
+
+    /// Replaces the current version with the one UPDATE builds from it.
+    /// Batching several changes into one update swaps only once.
+    pub fn update<F>(&self, update: F)
+    where
+        F: FnOnce(&persistent::Tree<T>) -> persistent::Tree<T>,
+    {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let next = update(&self.load());
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        let previous = mem::replace(&mut *current, next);
+
+        // Nodes only used by the previous version are freed without
+        // locking readers out.
+        drop(current);
+        drop(previous);
+    }
+}
+
+impl<T: Ord + Clone> Tree<T> {
+    #[must_use]
+    pub fn contains(&self, value: &T) -> bool {
+        self.load().contains(value)
+    }
+
+    /// Returns the values within BOUNDS in ascending order, as of when
+    /// the call started.
+    #[must_use]
+    pub fn range<R: RangeBounds<T>>(&self, bounds: R) -> Vec<T> {
+        self.load().range(bounds).cloned().collect()
+    }
+
+    /// Inserts VALUE, returning true if it wasn't in the tree.
+    pub fn insert(&self, value: T) -> bool {
+        let mut inserted = false;
+        self.update(|tree| {
+            let next = tree.insert(value);
+            inserted = !next.ptr_eq(tree);
+            next
+        });
+        inserted
+    }
+
+    /// Removes VALUE, returning true if it was in the tree.
+    pub fn remove(&self, value: &T) -> bool {
+        let mut removed = false;
+        self.update(|tree| {
+            let next = tree.remove(value);
+            removed = !next.ptr_eq(tree);
+            next
+        });
+        removed
+    }
+}
+
+impl<T> Default for Tree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
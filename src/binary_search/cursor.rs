@@ -0,0 +1,209 @@
+use super::{Augment, SlotIndex, Tree};
+use std::cmp::Ordering;
+
+impl<T, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    // Returns the index after CURRENT, or of the smallest value when
+    // CURRENT is past the ends.
+    pub(crate) fn next_wrapping(&self, current: Option<usize>) -> Option<usize> {
+        match current {
+            Some(n) => self.successor(n),
+            None => (!self.is_empty()).then(|| self.min_index(self.root)),
+        }
+    }
+
+    // Returns the index before CURRENT, or of the largest value when
+    // CURRENT is past the ends.
+    pub(crate) fn prev_wrapping(&self, current: Option<usize>) -> Option<usize> {
+        match current {
+            Some(n) => self.predecessor(n),
+            None => (!self.is_empty()).then(|| self.max_index(self.root)),
+        }
+    }
+}
+
+/// A cursor over a `Tree` that moves between neighboring values in both
+/// directions, taking O(1) amortized steps without building an iterator.
+/// Past either end, it points to no value, between the largest and
+/// smallest.
+pub struct Cursor<'a, T, I = u32, A = ()> {
+    tree: &'a Tree<T, I, A>,
+    current: Option<usize>,
+}
+
+// Derived impls would require T: Clone.
+impl<T, I, A> Clone for Cursor<'_, T, I, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, I, A> Copy for Cursor<'_, T, I, A> {}
+
+impl<'a, T, I: SlotIndex, A: Augment<T>> Cursor<'a, T, I, A> {
+    pub(crate) const fn new(tree: &'a Tree<T, I, A>, current: Option<usize>) -> Self {
+        Self { tree, current }
+    }
+
+    /// Returns the index of the current value, or None past the ends.
+    #[must_use]
+    pub const fn index(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Returns the current value, or None past the ends.
+    #[must_use]
+    pub fn current(&self) -> Option<&'a T> {
+        self.current.map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Returns the value after the current one without moving.
+    #[must_use]
+    pub fn peek_next(&self) -> Option<&'a T> {
+        let next = self.tree.next_wrapping(self.current);
+        next.map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Returns the value before the current one without moving.
+    #[must_use]
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        let prev = self.tree.prev_wrapping(self.current);
+        prev.map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Moves to the next larger value. Past the ends, moves to the
+    /// smallest.
+    pub fn move_next(&mut self) {
+        self.current = self.tree.next_wrapping(self.current);
+    }
+
+    /// Moves to the next smaller value. Past the ends, moves to the
+    /// largest.
+    pub fn move_prev(&mut self) {
+        self.current = self.tree.prev_wrapping(self.current);
+    }
+}
+
+/// A cursor over a `Tree` that can move between neighboring values and
+/// edit the tree where it points, without searching from the root. Past
+/// either end, it points to no value, between the largest and smallest.
+pub struct CursorMut<'a, T, I = u32, A = ()> {
+    tree: &'a mut Tree<T, I, A>,
+    current: Option<usize>,
+}
+
+impl<'a, T, I: SlotIndex, A: Augment<T>> CursorMut<'a, T, I, A> {
+    pub(crate) fn new(tree: &'a mut Tree<T, I, A>, current: Option<usize>) -> Self {
+        Self { tree, current }
+    }
+
+    /// Returns the index of the current value, or None past the ends.
+    #[must_use]
+    pub const fn index(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Returns the current value, or None past the ends.
+    #[must_use]
+    pub fn current(&self) -> Option<&T> {
+        self.current.map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Returns the value after the current one without moving.
+    #[must_use]
+    pub fn peek_next(&self) -> Option<&T> {
+        self.next_index().map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Returns the value before the current one without moving.
+    #[must_use]
+    pub fn peek_prev(&self) -> Option<&T> {
+        self.prev_index().map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Moves to the next larger value. Past the ends, moves to the
+    /// smallest.
+    pub fn move_next(&mut self) {
+        self.current = self.next_index();
+    }
+
+    /// Moves to the next smaller value. Past the ends, moves to the
+    /// largest.
+    pub fn move_prev(&mut self) {
+        self.current = self.prev_index();
+    }
+
+    /// Removes the current value and returns it, moving to the next
+    /// larger value. Past the ends, nothing is removed.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let index = self.current?;
+        // Removing only relinks nodes, so the next index stays valid.
+        self.current = self.tree.successor(index);
+        Some(self.tree.remove_index(index))
+    }
+
+    fn next_index(&self) -> Option<usize> {
+        self.tree.next_wrapping(self.current)
+    }
+
+    fn prev_index(&self) -> Option<usize> {
+        self.tree.prev_wrapping(self.current)
+    }
+}
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>> CursorMut<'_, T, I, A> {
+    /// Inserts VALUE right after the current value without moving, or
+    /// as the smallest value past the ends. Returns the index that was
+    /// used, or VALUE back if it isn't strictly between the current value
+    /// and the next.
+    pub fn insert_after(&mut self, value: T) -> Result<usize, T> {
+        let next = self.next_index();
+        if !self.fits_between(self.current, next, &value) {
+            return Err(value);
+        }
+        Ok(self.attach_between(self.current, next, value))
+    }
+
+    /// Inserts VALUE right before the current value without moving, or
+    /// as the largest value past the ends. Returns the index that was
+    /// used, or VALUE back if it isn't strictly between the previous value
+    /// and the current one.
+    pub fn insert_before(&mut self, value: T) -> Result<usize, T> {
+        let prev = self.prev_index();
+        if !self.fits_between(prev, self.current, &value) {
+            return Err(value);
+        }
+        Ok(self.attach_between(prev, self.current, value))
+    }
+
+    // Attaches VALUE between the neighboring values at LOWER and UPPER,
+    // either of which may be missing at the ends. One of them has a free
+    // child on the side facing the other. Returns the index that was used.
+    fn attach_between(&mut self, lower: Option<usize>, upper: Option<usize>, value: T) -> usize {
+        if self.tree.is_empty() {
+            return self.tree.insert_root(value);
+        }
+
+        match (lower, upper) {
+            (Some(n), _) if self.tree.slab[n].right().is_none() => {
+                self.tree.attach(n, Ordering::Greater, value)
+            }
+            (_, Some(n)) => self.tree.attach(n, Ordering::Less, value),
+            (_, None) => unreachable!(),
+        }
+    }
+
+    fn fits_between(&self, lower: Option<usize>, upper: Option<usize>, value: &T) -> bool {
+        let slab = &self.tree.slab;
+        lower.is_none_or(|n| *value > slab[n].value) && upper.is_none_or(|n| *value < slab[n].value)
+    }
+}
+
+impl<T, I: SlotIndex> CursorMut<'_, T, I> {
+    /// Returns a mutable reference to the current value, or None past the
+    /// ends. Changing its order relative to its neighbors leaves the tree
+    /// in an invalid state. Augmented trees don't allow this, like
+    /// `iter_mut`.
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        self.current.map(|n| &mut self.tree.slab[n].value)
+    }
+}
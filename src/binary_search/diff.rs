@@ -0,0 +1,254 @@
+use super::{Augment, Iter, SlotIndex, Tree};
+use std::cmp::Ordering;
+use std::iter::{FusedIterator, Peekable};
+use std::ops::Bound;
+
+/// Where a value was found by `Tree::diff`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffItem<'a, T> {
+    /// The value is only in the tree `diff` was called on.
+    Left(&'a T),
+    /// The value is only in the other tree.
+    Right(&'a T),
+    /// Equal values are in both trees, the left one first.
+    Both(&'a T, &'a T),
+}
+
+/// An iterator over the values of two trees in ascending order, saying
+/// which trees hold each one. Returned by `Tree::diff`.
+pub struct Diff<'a, T, I: SlotIndex = u32, A = (), J: SlotIndex = u32, B = ()> {
+    left: Peekable<Iter<'a, T, I, A>>,
+    right: Peekable<Iter<'a, T, J, B>>,
+}
+
+/// An iterator over the values in both of two trees in ascending order.
+/// Returned by `Tree::intersection_iter`.
+pub struct Intersection<'a, T, I: SlotIndex = u32, A = (), J: SlotIndex = u32, B = ()> {
+    left: &'a Tree<T, I, A>,
+    right: &'a Tree<T, J, B>,
+    // The next index of each tree to compare.
+    next_left: Option<usize>,
+    next_right: Option<usize>,
+    // Whether to find the next candidates by searching from the root
+    // rather than stepping to the successor.
+    leapfrog: bool,
+}
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Returns an iterator that walks this tree and OTHER together in
+    /// ascending order. It takes O(n + m) to exhaust, rather than looking
+    /// up every value. Equal values kept by `KeepBoth` are paired up one
+    /// by one.
+    pub fn diff<'a, J: SlotIndex, B: Augment<T>>(
+        &'a self,
+        other: &'a Tree<T, J, B>,
+    ) -> Diff<'a, T, I, A, J, B> {
+        Diff {
+            left: self.iter().peekable(),
+            right: other.iter().peekable(),
+        }
+    }
+
+    /// Returns an iterator over the values in this tree or OTHER in
+    /// ascending order, without building a tree. A value in both is
+    /// yielded once, from this tree.
+    pub fn union_iter<'a, J: SlotIndex, B: Augment<T>>(
+        &'a self,
+        other: &'a Tree<T, J, B>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.diff(other).map(|item| match item {
+            DiffItem::Left(value) | DiffItem::Right(value) | DiffItem::Both(value, _) => value,
+        })
+    }
+
+    /// Returns a tree of the values in this tree or OTHER, with this
+    /// tree's policies. It's built in O(n + m) from a merge of both.
+    #[must_use]
+    pub fn union<J: SlotIndex, B: Augment<T>>(&self, other: &Tree<T, J, B>) -> Self
+    where
+        T: Clone,
+    {
+        self.with_sorted(self.union_iter(other).cloned().collect())
+    }
+
+    /// Like `union`, but moves the values out of both trees rather than
+    /// cloning them.
+    #[must_use]
+    pub fn into_union(self, other: Self) -> Self {
+        let (shrink_policy, duplicate_policy) = (self.shrink_policy, self.duplicate_policy);
+        let mut right = other.into_sorted_vec().into_iter().peekable();
+        let mut values = Vec::with_capacity(self.size + right.len());
+        for value in self.into_sorted_vec() {
+            while let Some(next) = right.next_if(|next| *next < value) {
+                values.push(next);
+            }
+            // A value in both is kept from this tree.
+            right.next_if(|next| *next == value);
+            values.push(value);
+        }
+        values.extend(right);
+
+        let mut tree = Self::from_sorted_unchecked(values);
+        tree.shrink_policy = shrink_policy;
+        tree.duplicate_policy = duplicate_policy;
+        tree
+    }
+
+    /// Returns an iterator over the values in both this tree and OTHER in
+    /// ascending order, taken from this tree. It steps through both trees
+    /// together in O(n + m), or when one is much smaller, searches each
+    /// for the other's next value in O(min(n, m) log max(n, m)).
+    pub fn intersection_iter<'a, J: SlotIndex, B: Augment<T>>(
+        &'a self,
+        other: &'a Tree<T, J, B>,
+    ) -> Intersection<'a, T, I, A, J, B> {
+        let (small, large) = (self.len().min(other.len()), self.len().max(other.len()));
+        let log = (usize::BITS - large.leading_zeros()) as usize;
+        Intersection {
+            left: self,
+            right: other,
+            next_left: (!self.is_empty()).then(|| self.min_index(self.root)),
+            next_right: (!other.is_empty()).then(|| other.min_index(other.root)),
+            leapfrog: small * log < small + large,
+        }
+    }
+
+    /// Returns a tree of the values in both this tree and OTHER, with this
+    /// tree's policies. See `intersection_iter`.
+    #[must_use]
+    pub fn intersection<J: SlotIndex, B: Augment<T>>(&self, other: &Tree<T, J, B>) -> Self
+    where
+        T: Clone,
+    {
+        self.with_sorted(self.intersection_iter(other).cloned().collect())
+    }
+
+    /// Returns an iterator over the values in this tree but not in OTHER,
+    /// in ascending order. Takes O(n + m) to exhaust.
+    pub fn difference_iter<'a, J: SlotIndex, B: Augment<T>>(
+        &'a self,
+        other: &'a Tree<T, J, B>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.diff(other).filter_map(|item| match item {
+            DiffItem::Left(value) => Some(value),
+            DiffItem::Right(_) | DiffItem::Both(..) => None,
+        })
+    }
+
+    /// Returns a tree of the values in this tree but not in OTHER, with
+    /// this tree's policies.
+    #[must_use]
+    pub fn difference<J: SlotIndex, B: Augment<T>>(&self, other: &Tree<T, J, B>) -> Self
+    where
+        T: Clone,
+    {
+        self.with_sorted(self.difference_iter(other).cloned().collect())
+    }
+
+    /// Returns an iterator over the values in exactly one of this tree and
+    /// OTHER, in ascending order. Takes O(n + m) to exhaust.
+    pub fn symmetric_difference_iter<'a, J: SlotIndex, B: Augment<T>>(
+        &'a self,
+        other: &'a Tree<T, J, B>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.diff(other).filter_map(|item| match item {
+            DiffItem::Left(value) | DiffItem::Right(value) => Some(value),
+            DiffItem::Both(..) => None,
+        })
+    }
+
+    /// Returns a tree of the values in exactly one of this tree and OTHER,
+    /// with this tree's policies.
+    #[must_use]
+    pub fn symmetric_difference<J: SlotIndex, B: Augment<T>>(&self, other: &Tree<T, J, B>) -> Self
+    where
+        T: Clone,
+    {
+        self.with_sorted(self.symmetric_difference_iter(other).cloned().collect())
+    }
+
+    /// Returns whether every value in this tree is also in OTHER. Stops at
+    /// the first one that isn't.
+    #[must_use]
+    pub fn is_subset<J: SlotIndex, B: Augment<T>>(&self, other: &Tree<T, J, B>) -> bool {
+        self.len() <= other.len() && self.difference_iter(other).next().is_none()
+    }
+
+    /// Returns whether every value in OTHER is also in this tree.
+    #[must_use]
+    pub fn is_superset<J: SlotIndex, B: Augment<T>>(&self, other: &Tree<T, J, B>) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns whether no value is in both this tree and OTHER. Stops at
+    /// the first one that is, searching like `intersection_iter`.
+    #[must_use]
+    pub fn is_disjoint<J: SlotIndex, B: Augment<T>>(&self, other: &Tree<T, J, B>) -> bool {
+        self.intersection_iter(other).next().is_none()
+    }
+
+    // Builds a tree of VALUES, which are sorted, with this tree's
+    // policies.
+    fn with_sorted(&self, values: Vec<T>) -> Self {
+        let mut tree = Self::from_sorted_unchecked(values);
+        tree.shrink_policy = self.shrink_policy;
+        tree.duplicate_policy = self.duplicate_policy;
+        tree
+    }
+}
+
+impl<'a, T: Ord, I: SlotIndex, A, J: SlotIndex, B> Iterator for Diff<'a, T, I, A, J, B> {
+    type Item = DiffItem<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ordering = match (self.left.peek(), self.right.peek()) {
+            (Some(left), Some(right)) => left.cmp(right),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None,
+        };
+
+        Some(match ordering {
+            Ordering::Less => DiffItem::Left(self.left.next()?),
+            Ordering::Greater => DiffItem::Right(self.right.next()?),
+            Ordering::Equal => DiffItem::Both(self.left.next()?, self.right.next()?),
+        })
+    }
+}
+
+impl<T: Ord, I: SlotIndex, A, J: SlotIndex, B> FusedIterator for Diff<'_, T, I, A, J, B> {}
+
+impl<'a, T: Ord, I: SlotIndex, A: Augment<T>, J: SlotIndex, B: Augment<T>> Iterator
+    for Intersection<'a, T, I, A, J, B>
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let left = &self.left.slab[self.next_left?].value;
+            let right = &self.right.slab[self.next_right?].value;
+
+            match left.cmp(right) {
+                Ordering::Less if self.leapfrog => {
+                    self.next_left = self.left.lower_bound_index(Bound::Included(right));
+                }
+                Ordering::Less => self.next_left = self.left.successor(self.next_left?),
+                Ordering::Greater if self.leapfrog => {
+                    self.next_right = self.right.lower_bound_index(Bound::Included(left));
+                }
+                Ordering::Greater => self.next_right = self.right.successor(self.next_right?),
+                // Equal values kept by `KeepBoth` are paired up one by one.
+                Ordering::Equal => {
+                    self.next_left = self.left.successor(self.next_left?);
+                    self.next_right = self.right.successor(self.next_right?);
+                    return Some(left);
+                }
+            }
+        }
+    }
+}
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>, J: SlotIndex, B: Augment<T>> FusedIterator
+    for Intersection<'_, T, I, A, J, B>
+{
+}
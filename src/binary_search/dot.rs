@@ -0,0 +1,71 @@
+use super::{Augment, SlotIndex, Tree};
+use std::fmt::{self, Display, Write};
+
+impl<T: Display, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Returns a Graphviz DOT graph of the tree's shape, labelling every
+    /// node with its value, index and height. A node with one child gets
+    /// an invisible one on its other side, so left and right children are
+    /// drawn where they belong. Only with the `dot` feature.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        match self.write_dot(&mut dot) {
+            Ok(()) => dot,
+            // Writing to a String never fails.
+            Err(_) => unreachable!(),
+        }
+    }
+
+    fn write_dot(&self, dot: &mut String) -> fmt::Result {
+        writeln!(dot, "digraph {{")?;
+        writeln!(dot, "    node [shape=box];")?;
+
+        let mut stack: Vec<usize> = (!self.is_empty())
+            .then_some(self.root)
+            .into_iter()
+            .collect();
+        while let Some(index) = stack.pop() {
+            let node_data = &self.slab[index];
+            let value = escape(&node_data.value.to_string());
+            let height = node_data.height;
+            writeln!(
+                dot,
+                "    n{index} [label=\"{value}\\nindex {index}, height {height}\"];"
+            )?;
+
+            let children = [node_data.left(), node_data.right()];
+            if children == [None, None] {
+                continue;
+            }
+            for (side, child) in ["left", "right"].into_iter().zip(children) {
+                match child {
+                    Some(n) => writeln!(dot, "    n{index} -> n{n};")?,
+                    None => {
+                        writeln!(dot, "    n{index}_{side} [style=invis];")?;
+                        writeln!(dot, "    n{index} -> n{index}_{side} [style=invis];")?;
+                    }
+                }
+            }
+            // Pushing the right child first visits the left one first.
+            stack.extend(children.into_iter().rev().flatten());
+        }
+
+        writeln!(dot, "}}")
+    }
+}
+
+// Escapes TEXT for a double-quoted DOT string.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
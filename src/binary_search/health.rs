@@ -0,0 +1,56 @@
+use super::{Augment, SlotIndex, Tree};
+
+/// How well a tree is balanced and how much of its storage is used.
+/// Returned by `Tree::health`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Health {
+    /// The number of values at each depth, starting with the root at 0.
+    pub depths: Vec<usize>,
+    /// The mean depth of the values, or 0 if there are none.
+    pub average_depth: f64,
+    /// The number of values, as returned by `len`.
+    pub len: usize,
+    /// The number of slots, whether they hold a value or not.
+    pub slots: usize,
+    /// The number of vacant slots, which `trim` or `compact` can drop.
+    pub vacant: usize,
+    /// The bytes allocated for slots that don't hold a value.
+    pub wasted_bytes: usize,
+}
+
+impl<T, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Returns a report on the tree's shape and storage, to help decide
+    /// when to call `compact` or rebuild it. Takes O(n).
+    #[must_use]
+    pub fn health(&self) -> Health {
+        let mut depths = Vec::new();
+        let mut stack = Vec::new();
+        if !self.is_empty() {
+            stack.push((self.root, 0));
+        }
+        while let Some((index, depth)) = stack.pop() {
+            if depths.len() == depth {
+                depths.push(0);
+            }
+            depths[depth] += 1;
+
+            let node = &self.slab[index];
+            stack.extend(node.left().map(|n| (n, depth + 1)));
+            stack.extend(node.right().map(|n| (n, depth + 1)));
+        }
+
+        let total: usize = depths.iter().enumerate().map(|(d, n)| d * n).sum();
+        Health {
+            average_depth: if self.is_empty() {
+                0.0
+            } else {
+                total as f64 / self.len() as f64
+            },
+            depths,
+            len: self.len(),
+            slots: self.slab.len(),
+            vacant: self.slab.len() - self.len(),
+            wasted_bytes: self.slab.wasted_bytes(self.len()),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::fmt;
+
+/// An unsigned integer type used to link nodes together. Narrower types
+/// make every node smaller, but limit a tree to `SlotIndex::MAX` slots.
+pub trait SlotIndex: Copy + Eq + fmt::Debug {
+    /// The number of slots a tree using this index type can hold.
+    const MAX: usize;
+
+    /// The largest value of the type, which never refers to a slot.
+    const NONE: Self;
+
+    /// Converts N, which must be less than `MAX`.
+    fn from_usize(n: usize) -> Self;
+
+    fn to_usize(self) -> usize;
+}
+
+macro_rules! impl_slot_index {
+    ($($t:ty),*) => {$(
+        impl SlotIndex for $t {
+            // The largest value is never used as an index.
+            const MAX: usize = if <$t>::MAX as u128 > usize::MAX as u128 {
+                usize::MAX
+            } else {
+                <$t>::MAX as usize
+            };
+
+            const NONE: Self = <$t>::MAX;
+
+            #[allow(clippy::cast_possible_truncation)]
+            fn from_usize(n: usize) -> Self {
+                debug_assert!(n < <Self as SlotIndex>::MAX);
+                n as $t
+            }
+
+            #[allow(clippy::cast_possible_truncation)]
+            fn to_usize(self) -> usize {
+                self as usize
+            }
+        }
+    )*};
+}
+
+impl_slot_index!(u8, u16, u32, u64, usize);
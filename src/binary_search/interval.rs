@@ -0,0 +1,194 @@
+use super::{Augment, Tree};
+use std::cmp::{self, Ordering};
+use std::ops::{Bound, Range};
+
+// Ordered by the range's start, then its end. The value is ignored, so
+// that one range maps to at most one value.
+#[derive(Clone)]
+struct Entry<T, V> {
+    range: Range<T>,
+    value: V,
+}
+
+impl<T: Ord, V> Entry<T, V> {
+    fn cmp_range(&self, range: &Range<T>) -> Ordering {
+        (&self.range.start, &self.range.end).cmp(&(&range.start, &range.end))
+    }
+}
+
+impl<T: Ord, V> PartialEq for Entry<T, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord, V> Eq for Entry<T, V> {}
+
+impl<T: Ord, V> PartialOrd for Entry<T, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord, V> Ord for Entry<T, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_range(&other.range)
+    }
+}
+
+// The largest end of any range in a subtree.
+#[derive(Clone, PartialEq)]
+struct MaxEnd<T>(T);
+
+impl<T: Ord + Clone, V> Augment<Entry<T, V>> for MaxEnd<T> {
+    fn combine(left: Option<&Self>, entry: &Entry<T, V>, right: Option<&Self>) -> Self {
+        let mut max = &entry.range.end;
+        for side in [left, right].into_iter().flatten() {
+            max = cmp::max(max, &side.0);
+        }
+        Self(max.clone())
+    }
+}
+
+/// A map from half-open ranges to values, which finds every range that
+/// overlaps a query in O(log n + k) for k results. Each subtree keeps the
+/// largest end of its ranges, so subtrees that end too early are skipped.
+#[derive(Clone)]
+pub struct IntervalTree<T, V> {
+    tree: Tree<Entry<T, V>, u32, MaxEnd<T>>,
+}
+
+impl<T: Ord + Clone, V> IntervalTree<T, V> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { tree: Tree::new() }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.tree.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.tree.size == 0
+    }
+
+    /// Maps RANGE to VALUE. Returns the value RANGE mapped to before,
+    /// if any. Empty ranges are stored, but never overlap anything.
+    pub fn insert(&mut self, range: Range<T>, value: V) -> Option<V> {
+        match self.find(&range) {
+            Some(index) => {
+                // Replacing the value keeps the range, so the augments
+                // stay the same.
+                let entry = &mut self.tree.slab[index].value;
+                Some(std::mem::replace(&mut entry.value, value))
+            }
+            None => {
+                self.tree.insert(Entry { range, value });
+                None
+            }
+        }
+    }
+
+    /// Removes RANGE and returns the value it mapped to, if any.
+    pub fn remove(&mut self, range: &Range<T>) -> Option<V> {
+        let index = self.find(range)?;
+        Some(self.tree.remove_index(index).value)
+    }
+
+    /// Returns the value RANGE maps to, if any.
+    #[must_use]
+    pub fn get(&self, range: &Range<T>) -> Option<&V> {
+        self.find(range).map(|n| &self.tree.slab[n].value.value)
+    }
+
+    /// Returns every range that shares at least one point with RANGE,
+    /// along with its value, ordered by start and then end.
+    #[must_use]
+    pub fn find_overlapping(&self, range: &Range<T>) -> Vec<(&Range<T>, &V)> {
+        let mut found = Vec::new();
+        if !self.is_empty() && !range.is_empty() {
+            self.collect(
+                self.tree.root,
+                &range.start,
+                Bound::Excluded(&range.end),
+                &mut found,
+            );
+        }
+        found
+    }
+
+    /// Returns every range that contains POINT, along with its value,
+    /// ordered by start and then end.
+    #[must_use]
+    pub fn stab(&self, point: &T) -> Vec<(&Range<T>, &V)> {
+        let mut found = Vec::new();
+        if !self.is_empty() {
+            self.collect(self.tree.root, point, Bound::Included(point), &mut found);
+        }
+        found
+    }
+
+    /// Returns an iterator over the ranges and their values, ordered by
+    /// start and then end.
+    pub fn iter(&self) -> impl Iterator<Item = (&Range<T>, &V)> {
+        self.tree.iter().map(|entry| (&entry.range, &entry.value))
+    }
+
+    fn find(&self, range: &Range<T>) -> Option<usize> {
+        let mut current = (!self.is_empty()).then_some(self.tree.root);
+        while let Some(n) = current {
+            let node = &self.tree.slab[n];
+            current = match node.value.cmp_range(range) {
+                Ordering::Greater => node.left(),
+                Ordering::Less => node.right(),
+                Ordering::Equal => return Some(n),
+            };
+        }
+        None
+    }
+
+    // Pushes every range in the subtree at INDEX that ends after START
+    // and starts within END, in order.
+    fn collect<'a>(
+        &'a self,
+        index: usize,
+        start: &T,
+        end: Bound<&T>,
+        found: &mut Vec<(&'a Range<T>, &'a V)>,
+    ) {
+        let node = &self.tree.slab[index];
+        if node.augment.0 <= *start {
+            return;
+        }
+
+        if let Some(n) = node.left() {
+            self.collect(n, start, end, found);
+        }
+
+        // Ranges to the right start at or after this one.
+        let entry = &node.value;
+        let starts_within = match end {
+            Bound::Included(end) => entry.range.start <= *end,
+            Bound::Excluded(end) => entry.range.start < *end,
+            Bound::Unbounded => true,
+        };
+        if !starts_within {
+            return;
+        }
+
+        if entry.range.end > *start && !entry.range.is_empty() {
+            found.push((&entry.range, &entry.value));
+        }
+        if let Some(n) = node.right() {
+            self.collect(n, start, end, found);
+        }
+    }
+}
+
+impl<T: Ord + Clone, V> Default for IntervalTree<T, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
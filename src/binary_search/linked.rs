@@ -0,0 +1,437 @@
+use super::slab::Slab;
+use super::{ordered, pack_link, unpack_link, InvariantError, SlotIndex};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::{Bound, RangeBounds};
+
+// A node linked to its children and parent, with metadata M that the
+// tree using it keeps balanced with. Missing links are `I::NONE`.
+#[derive(Clone)]
+pub(crate) struct Node<T, I, M> {
+    pub value: T,
+    left: I,
+    right: I,
+    parent: I,
+    pub meta: M,
+}
+
+impl<T, I: SlotIndex, M> Node<T, I, M> {
+    pub const fn new(value: T, meta: M) -> Self {
+        Self {
+            value,
+            left: I::NONE,
+            right: I::NONE,
+            parent: I::NONE,
+            meta,
+        }
+    }
+
+    pub fn left(&self) -> Option<usize> {
+        unpack_link(self.left)
+    }
+
+    pub fn right(&self) -> Option<usize> {
+        unpack_link(self.right)
+    }
+
+    pub fn parent(&self) -> Option<usize> {
+        unpack_link(self.parent)
+    }
+
+    pub fn set_left(&mut self, link: Option<usize>) {
+        self.left = pack_link(link);
+    }
+
+    pub fn set_right(&mut self, link: Option<usize>) {
+        self.right = pack_link(link);
+    }
+
+    pub fn set_parent(&mut self, link: Option<usize>) {
+        self.parent = pack_link(link);
+    }
+
+    // Relinks the node through REMAP, a table from old to new indices.
+    fn remap(&mut self, remap: impl Fn(usize) -> usize) {
+        self.set_left(self.left().map(&remap));
+        self.set_right(self.right().map(&remap));
+        self.set_parent(self.parent().map(&remap));
+    }
+}
+
+// The links shared by the slab trees other than `Tree`, which only differ
+// in how they balance. Nothing here changes a node's metadata.
+#[derive(Clone)]
+pub(crate) struct Linked<T, I, M> {
+    pub slab: Slab<Node<T, I, M>, I>,
+    pub root: usize,
+    pub size: usize,
+}
+
+// What `Linked::unlink` left behind.
+pub(crate) struct Unlinked {
+    // The node that took the unlinked node's place, when it had two
+    // children.
+    pub moved: Option<usize>,
+    // The subtree that moved up to where a node was really detached,
+    // and its new parent. This is the lowest point that changed.
+    pub child: Option<usize>,
+    pub child_parent: Option<usize>,
+}
+
+impl<T, I: SlotIndex, M> Linked<T, I, M> {
+    pub const fn new() -> Self {
+        Self {
+            slab: Slab::new(),
+            root: 0,
+            size: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slab: Slab::with_capacity(capacity),
+            ..Self::new()
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.slab.get(index).map(|n| &n.value)
+    }
+
+    // Returns an iterator over the indices in ascending order of values.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.subtree_indices((self.size != 0).then_some(self.root))
+    }
+
+    pub fn min_index(&self, mut index: usize) -> usize {
+        while let Some(n) = self.slab[index].left() {
+            index = n;
+        }
+        index
+    }
+
+    pub fn max_index(&self, mut index: usize) -> usize {
+        while let Some(n) = self.slab[index].right() {
+            index = n;
+        }
+        index
+    }
+
+    // Returns the index of the next larger value, if any.
+    pub fn successor(&self, mut index: usize) -> Option<usize> {
+        if let Some(n) = self.slab[index].right() {
+            return Some(self.min_index(n));
+        }
+        while let Some(n) = self.slab[index].parent() {
+            if self.slab[n].left() == Some(index) {
+                return Some(n);
+            }
+            index = n;
+        }
+        None
+    }
+
+    // Makes NEW take the place of the subtree at OLD under OLD's parent.
+    pub fn transplant(&mut self, old: usize, new: Option<usize>) {
+        let parent = self.slab[old].parent();
+        match parent {
+            Some(n) if self.slab[n].left() == Some(old) => self.slab[n].set_left(new),
+            Some(n) => self.slab[n].set_right(new),
+            None => {
+                if let Some(new) = new {
+                    self.root = new;
+                }
+            }
+        }
+        if let Some(n) = new {
+            self.slab[n].set_parent(parent);
+        }
+    }
+
+    // The right child of INDEX takes its place, and INDEX becomes its
+    // left child. Returns the right child's index.
+    pub fn rotate_left(&mut self, index: usize) -> usize {
+        let Some(right) = self.slab[index].right() else {
+            unreachable!()
+        };
+        let middle = self.slab[right].left();
+
+        self.slab[index].set_right(middle);
+        if let Some(n) = middle {
+            self.slab[n].set_parent(Some(index));
+        }
+        self.transplant(index, Some(right));
+        self.slab[right].set_left(Some(index));
+        self.slab[index].set_parent(Some(right));
+        right
+    }
+
+    // The left child of INDEX takes its place, and INDEX becomes its
+    // right child. Returns the left child's index.
+    pub fn rotate_right(&mut self, index: usize) -> usize {
+        let Some(left) = self.slab[index].left() else {
+            unreachable!()
+        };
+        let middle = self.slab[left].right();
+
+        self.slab[index].set_left(middle);
+        if let Some(n) = middle {
+            self.slab[n].set_parent(Some(index));
+        }
+        self.transplant(index, Some(left));
+        self.slab[left].set_right(Some(index));
+        self.slab[index].set_parent(Some(left));
+        left
+    }
+
+    // Stores NODE as a leaf on the ORDERING side of PARENT, or as the
+    // root without a parent. Returns its index.
+    pub fn attach(
+        &mut self,
+        parent: Option<usize>,
+        ordering: Ordering,
+        node: Node<T, I, M>,
+    ) -> usize {
+        let index = self.slab.insert(node);
+        self.size += 1;
+        match parent {
+            None => self.root = index,
+            Some(n) => {
+                self.slab[index].set_parent(Some(n));
+                if ordering == Ordering::Less {
+                    self.slab[n].set_left(Some(index));
+                } else {
+                    self.slab[n].set_right(Some(index));
+                }
+            }
+        }
+        index
+    }
+
+    // Unlinks the node at INDEX from the tree, leaving it in its slot.
+    // With two children, its successor takes its place.
+    pub fn unlink(&mut self, index: usize) -> Unlinked {
+        let node_data = &self.slab[index];
+        let (left, right, parent) = (node_data.left(), node_data.right(), node_data.parent());
+        self.size -= 1;
+
+        match (left, right) {
+            (None, _) | (_, None) => {
+                let child = left.or(right);
+                self.transplant(index, child);
+                Unlinked {
+                    moved: None,
+                    child,
+                    child_parent: parent,
+                }
+            }
+            (Some(left), Some(right)) => {
+                let successor = self.min_index(right);
+                let child = self.slab[successor].right();
+
+                let child_parent = if successor == right {
+                    successor
+                } else {
+                    let Some(successor_parent) = self.slab[successor].parent() else {
+                        unreachable!()
+                    };
+                    self.transplant(successor, child);
+                    self.slab[successor].set_right(Some(right));
+                    self.slab[right].set_parent(Some(successor));
+                    successor_parent
+                };
+
+                self.transplant(index, Some(successor));
+                self.slab[successor].set_left(Some(left));
+                self.slab[left].set_parent(Some(successor));
+                Unlinked {
+                    moved: Some(successor),
+                    child,
+                    child_parent: Some(child_parent),
+                }
+            }
+        }
+    }
+
+    // Returns an iterator over the indices of the subtree at ROOT in
+    // ascending order of values. ROOT must have no parent.
+    pub fn subtree_indices(&self, root: Option<usize>) -> impl Iterator<Item = usize> + '_ {
+        let first = root.map(|n| self.min_index(n));
+        std::iter::successors(first, |&n| self.successor(n))
+    }
+
+    // Points INDEX's left link to CHILD, and CHILD back.
+    pub fn link_left(&mut self, index: usize, child: Option<usize>) {
+        self.slab[index].set_left(child);
+        if let Some(n) = child {
+            self.slab[n].set_parent(Some(index));
+        }
+    }
+
+    // Points INDEX's right link to CHILD, and CHILD back.
+    pub fn link_right(&mut self, index: usize, child: Option<usize>) {
+        self.slab[index].set_right(child);
+        if let Some(n) = child {
+            self.slab[n].set_parent(Some(index));
+        }
+    }
+
+    // Moves the LEN nodes of the subtree at ROOT, which must have no
+    // parent, into a tree of their own. Takes O(LEN).
+    pub fn take_subtree(&mut self, root: usize, len: usize) -> Self {
+        let order: Vec<usize> = self.subtree_indices(Some(root)).collect();
+        debug_assert_eq!(order.len(), len);
+        let remap: HashMap<usize, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(new, &old)| (old, new))
+            .collect();
+
+        let nodes = order
+            .iter()
+            .map(|&old_index| {
+                let mut node = self.slab.remove(old_index);
+                node.remap(|n| remap[&n]);
+                node
+            })
+            .collect();
+        self.size -= len;
+
+        Self {
+            slab: Slab::from_vec(nodes),
+            root: remap[&root],
+            size: len,
+        }
+    }
+
+    // Moves OTHER's nodes into this tree's slab, without linking them to
+    // its nodes. Returns OTHER's root in the slab.
+    pub fn absorb(&mut self, other: Self) -> usize {
+        let offset = self
+            .slab
+            .append(other.slab, |node, offset| node.remap(|n| n + offset));
+        self.size += other.size;
+        other.root + offset
+    }
+}
+
+impl<T: Ord, I: SlotIndex, M> Linked<T, I, M> {
+    // Returns the index of VALUE if it is found, otherwise the index of
+    // the node it would be attached to, if any, and which side it would
+    // go on.
+    pub fn search(&self, value: &T) -> Result<usize, (Option<usize>, Ordering)> {
+        if self.size == 0 {
+            return Err((None, Ordering::Equal));
+        }
+
+        let mut index = self.root;
+        loop {
+            let node_data = &self.slab[index];
+            let ordering = value.cmp(&node_data.value);
+            let next = match ordering {
+                Ordering::Less => node_data.left(),
+                Ordering::Greater => node_data.right(),
+                Ordering::Equal => return Ok(index),
+            };
+            match next {
+                Some(n) => index = n,
+                None => return Err((Some(index), ordering)),
+            }
+        }
+    }
+
+    // Returns an iterator over the values within BOUNDS in ascending
+    // order.
+    pub fn range<R: RangeBounds<T>>(&self, bounds: R) -> impl Iterator<Item = &T> {
+        let first = self.lower_bound(bounds.start_bound());
+        std::iter::successors(first, |&n| self.successor(n))
+            .map(|n| &self.slab[n].value)
+            .take_while(move |&value| ordered::below_upper(value, bounds.end_bound()))
+    }
+
+    // Returns the index of the smallest value within the LOWER bound, if
+    // any.
+    fn lower_bound(&self, lower: Bound<&T>) -> Option<usize> {
+        let mut found = None;
+        let mut current = (self.size != 0).then_some(self.root);
+        while let Some(n) = current {
+            let node_data = &self.slab[n];
+            if ordered::above_lower(&node_data.value, lower) {
+                found = Some(n);
+                current = node_data.left();
+            } else {
+                current = node_data.right();
+            }
+        }
+        found
+    }
+
+    // Checks that the values are strictly ordered, that every node is
+    // reachable from the root and linked to its parent, and that the
+    // vacant slots are all on the free list. CHECK is called on every
+    // node after its children, with what it returned for them, or LEAF
+    // for missing ones.
+    pub fn validate<R, F>(&self, leaf: R, mut check: F) -> Result<(), InvariantError>
+    where
+        R: Copy,
+        F: FnMut(usize, &Node<T, I, M>, R, R) -> Result<R, InvariantError>,
+    {
+        let mut visited = vec![false; self.slab.len()];
+        if self.size != 0 {
+            let root = Some(self.root);
+            self.validate_subtree(root, None, (None, None), &mut visited, leaf, &mut check)?;
+        }
+
+        for (index, &visited) in visited.iter().enumerate() {
+            if !visited && self.slab.get(index).is_some() {
+                return Err(InvariantError::Unreachable(index));
+            }
+        }
+        if visited.iter().filter(|&&n| n).count() != self.size {
+            return Err(InvariantError::Size);
+        }
+
+        match self.slab.find_free_list_error() {
+            Some(n) => Err(InvariantError::FreeList(n)),
+            None => Ok(()),
+        }
+    }
+
+    fn validate_subtree<R, F>(
+        &self,
+        index: Option<usize>,
+        parent: Option<usize>,
+        bounds: (Option<&T>, Option<&T>),
+        visited: &mut [bool],
+        leaf: R,
+        check: &mut F,
+    ) -> Result<R, InvariantError>
+    where
+        R: Copy,
+        F: FnMut(usize, &Node<T, I, M>, R, R) -> Result<R, InvariantError>,
+    {
+        let Some(index) = index else {
+            return Ok(leaf);
+        };
+        let Some(node_data) = self.slab.get(index) else {
+            return Err(InvariantError::Vacant(index));
+        };
+        if visited[index] || node_data.parent() != parent {
+            return Err(InvariantError::Parent(index));
+        }
+        visited[index] = true;
+
+        let value = &node_data.value;
+        if bounds.0.is_some_and(|n| value <= n) || bounds.1.is_some_and(|n| value >= n) {
+            return Err(InvariantError::Order(index));
+        }
+
+        let left = (bounds.0, Some(value));
+        let right = (Some(value), bounds.1);
+        let parent = Some(index);
+        let left = self.validate_subtree(node_data.left(), parent, left, visited, leaf, check)?;
+        let right =
+            self.validate_subtree(node_data.right(), parent, right, visited, leaf, check)?;
+        check(index, node_data, left, right)
+    }
+}
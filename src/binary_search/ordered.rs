@@ -0,0 +1,122 @@
+use super::{Augment, RbTree, ScapegoatTree, SlotIndex, Treap, Tree, WbTree};
+use std::ops::{Bound, RangeBounds};
+
+/// The operations shared by the slab trees, so that code can be generic
+/// over how they balance. Every method calls the tree's own method of the
+/// same name, and indices are slots in the tree's slab, as with `get`.
+pub trait OrderedSet<T: Ord> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the index of VALUE if it is found.
+    fn contains(&self, value: T) -> Option<usize>;
+
+    /// Inserts VALUE, returning its index, or None if it's already in
+    /// the set.
+    fn insert(&mut self, value: T) -> Option<usize>;
+
+    /// Removes VALUE from the set and returns it, if it was there.
+    fn remove(&mut self, value: T) -> Option<T>;
+
+    /// Returns an iterator over the values in ascending order.
+    fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a;
+
+    /// Returns an iterator over the values within BOUNDS in ascending
+    /// order. Finding the first takes O(log n).
+    fn range<'a, R: RangeBounds<T>>(&'a self, bounds: R) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a;
+}
+
+// Returns whether VALUE is within the LOWER bound.
+pub(crate) fn above_lower<T: Ord>(value: &T, lower: Bound<&T>) -> bool {
+    match lower {
+        Bound::Included(n) => value >= n,
+        Bound::Excluded(n) => value > n,
+        Bound::Unbounded => true,
+    }
+}
+
+// Returns whether VALUE is within the UPPER bound.
+pub(crate) fn below_upper<T: Ord>(value: &T, upper: Bound<&T>) -> bool {
+    match upper {
+        Bound::Included(n) => value <= n,
+        Bound::Excluded(n) => value < n,
+        Bound::Unbounded => true,
+    }
+}
+
+macro_rules! impl_ordered_set {
+    ($($tree:ident),+) => {$(
+        impl<T: Ord, I: SlotIndex> OrderedSet<T> for $tree<T, I> {
+            fn len(&self) -> usize {
+                self.len()
+            }
+
+            fn contains(&self, value: T) -> Option<usize> {
+                self.contains(value)
+            }
+
+            fn insert(&mut self, value: T) -> Option<usize> {
+                self.insert(value)
+            }
+
+            fn remove(&mut self, value: T) -> Option<T> {
+                self.remove(value)
+            }
+
+            fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+            where
+                T: 'a,
+            {
+                self.iter()
+            }
+
+            fn range<'a, R: RangeBounds<T>>(&'a self, bounds: R) -> impl Iterator<Item = &'a T>
+            where
+                T: 'a,
+            {
+                self.range(bounds)
+            }
+        }
+    )+};
+}
+
+impl_ordered_set!(RbTree, WbTree, Treap, ScapegoatTree);
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>> OrderedSet<T> for Tree<T, I, A> {
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn contains(&self, value: T) -> Option<usize> {
+        self.contains(value)
+    }
+
+    fn insert(&mut self, value: T) -> Option<usize> {
+        self.insert(value)
+    }
+
+    fn remove(&mut self, value: T) -> Option<T> {
+        self.remove(value)
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a,
+    {
+        self.iter()
+    }
+
+    fn range<'a, R: RangeBounds<T>>(&'a self, bounds: R) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a,
+    {
+        self.range(bounds)
+    }
+}
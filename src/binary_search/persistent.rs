@@ -0,0 +1,335 @@
+use std::cmp::{self, Ordering};
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+type Link<T> = Option<Arc<Node<T>>>;
+
+struct Node<T> {
+    value: T,
+    left: Link<T>,
+    right: Link<T>,
+    height: i8,
+}
+
+fn height<T>(link: &Link<T>) -> i8 {
+    link.as_ref().map_or(-1, |n| n.height)
+}
+
+fn make<T>(left: Link<T>, value: T, right: Link<T>) -> Arc<Node<T>> {
+    let height = 1 + cmp::max(height(&left), height(&right));
+    Arc::new(Node {
+        value,
+        left,
+        right,
+        height,
+    })
+}
+
+/// An AVL tree that is never changed in place. Inserting or removing
+/// returns a new version, which copies the nodes on the path to the
+/// change and shares every other node with the old version. Cloning a
+/// version is O(1).
+///
+/// Nodes are reference counted rather than stored in a slab, since a node
+/// shared by several versions has no single parent to link back to.
+pub struct Tree<T> {
+    root: Link<T>,
+    size: usize,
+}
+
+impl<T> Tree<T> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            root: None,
+            size: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            remaining: self.size,
+        };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+
+    /// Returns true if both versions share the same root, so they hold
+    /// the same values without comparing any.
+    #[must_use]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: Ord + Clone> Tree<T> {
+    #[must_use]
+    pub fn contains(&self, value: &T) -> bool {
+        let mut current = self.root.as_deref();
+        while let Some(node) = current {
+            current = match value.cmp(&node.value) {
+                Ordering::Less => node.left.as_deref(),
+                Ordering::Greater => node.right.as_deref(),
+                Ordering::Equal => return true,
+            };
+        }
+        false
+    }
+
+    /// Returns an iterator over the values within BOUNDS in ascending
+    /// order.
+    pub fn range<R: RangeBounds<T>>(&self, bounds: R) -> Range<'_, T, R> {
+        let mut stack = Vec::new();
+        let mut current = self.root.as_deref();
+        while let Some(node) = current {
+            let above_start = match bounds.start_bound() {
+                Bound::Included(n) => node.value >= *n,
+                Bound::Excluded(n) => node.value > *n,
+                Bound::Unbounded => true,
+            };
+            current = if above_start {
+                stack.push(node);
+                node.left.as_deref()
+            } else {
+                node.right.as_deref()
+            };
+        }
+
+        Range {
+            iter: Iter {
+                stack,
+                remaining: self.size,
+            },
+            bounds,
+        }
+    }
+
+    /// Returns a version that also holds VALUE. If VALUE is already in the
+    /// tree, the new version shares everything with this one.
+    #[must_use]
+    pub fn insert(&self, value: T) -> Self {
+        match insert(&self.root, value) {
+            Some(root) => Self {
+                root: Some(root),
+                size: self.size + 1,
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// Returns a version without VALUE. If VALUE isn't in the tree, the
+    /// new version shares everything with this one.
+    #[must_use]
+    pub fn remove(&self, value: &T) -> Self {
+        match remove(&self.root, value) {
+            Some(root) => Self {
+                root,
+                size: self.size - 1,
+            },
+            None => self.clone(),
+        }
+    }
+}
+
+// Returns the new root, or None if VALUE was already there.
+fn insert<T: Ord + Clone>(link: &Link<T>, value: T) -> Option<Arc<Node<T>>> {
+    let Some(node) = link else {
+        return Some(make(None, value, None));
+    };
+
+    match value.cmp(&node.value) {
+        Ordering::Less => {
+            let left = insert(&node.left, value)?;
+            Some(balance(Some(left), node.value.clone(), node.right.clone()))
+        }
+        Ordering::Greater => {
+            let right = insert(&node.right, value)?;
+            Some(balance(node.left.clone(), node.value.clone(), Some(right)))
+        }
+        Ordering::Equal => None,
+    }
+}
+
+// Returns the new root, or None if VALUE wasn't there.
+fn remove<T: Ord + Clone>(link: &Link<T>, value: &T) -> Option<Link<T>> {
+    let node = link.as_ref()?;
+
+    match value.cmp(&node.value) {
+        Ordering::Less => {
+            let left = remove(&node.left, value)?;
+            Some(Some(balance(left, node.value.clone(), node.right.clone())))
+        }
+        Ordering::Greater => {
+            let right = remove(&node.right, value)?;
+            Some(Some(balance(node.left.clone(), node.value.clone(), right)))
+        }
+        Ordering::Equal => Some(match (&node.left, &node.right) {
+            (None, right) => right.clone(),
+            (left, None) => left.clone(),
+            // The successor takes the removed value's place.
+            (left, Some(right)) => {
+                let (successor, right) = remove_min(right);
+                Some(balance(left.clone(), successor, right))
+            }
+        }),
+    }
+}
+
+// Returns the smallest value under NODE and the subtree without it.
+fn remove_min<T: Clone>(node: &Node<T>) -> (T, Link<T>) {
+    match &node.left {
+        Some(left) => {
+            let (min, left) = remove_min(left);
+            let node = balance(left, node.value.clone(), node.right.clone());
+            (min, Some(node))
+        }
+        None => (node.value.clone(), node.right.clone()),
+    }
+}
+
+// Builds a node from LEFT, VALUE and RIGHT, whose heights differ by at
+// most two, rotating if they differ by two.
+fn balance<T: Clone>(left: Link<T>, value: T, right: Link<T>) -> Arc<Node<T>> {
+    let (left_height, right_height) = (height(&left), height(&right));
+
+    if left_height > right_height + 1 {
+        let Some(left) = left else { unreachable!() };
+        if height(&left.left) >= height(&left.right) {
+            let right = make(left.right.clone(), value, right);
+            make(left.left.clone(), left.value.clone(), Some(right))
+        } else {
+            let Some(middle) = &left.right else {
+                unreachable!()
+            };
+            let new_left = make(left.left.clone(), left.value.clone(), middle.left.clone());
+            let new_right = make(middle.right.clone(), value, right);
+            make(Some(new_left), middle.value.clone(), Some(new_right))
+        }
+    } else if right_height > left_height + 1 {
+        let Some(right) = right else { unreachable!() };
+        if height(&right.right) >= height(&right.left) {
+            let left = make(left, value, right.left.clone());
+            make(Some(left), right.value.clone(), right.right.clone())
+        } else {
+            let Some(middle) = &right.left else {
+                unreachable!()
+            };
+            let new_left = make(left, value, middle.left.clone());
+            let new_right = make(
+                middle.right.clone(),
+                right.value.clone(),
+                right.right.clone(),
+            );
+            make(Some(new_left), middle.value.clone(), Some(new_right))
+        }
+    } else {
+        make(left, value, right)
+    }
+}
+
+impl<T> Clone for Tree<T> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            size: self.size,
+        }
+    }
+}
+
+impl<T> Default for Tree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<T> for Tree<T> {
+    fn from_iter<V: IntoIterator<Item = T>>(values: V) -> Self {
+        values
+            .into_iter()
+            .fold(Self::new(), |tree, value| tree.insert(value))
+    }
+}
+
+/// Iterates over a version's values in ascending order.
+pub struct Iter<'a, T> {
+    stack: Vec<&'a Node<T>>,
+    remaining: usize,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn push_left(&mut self, mut node: Option<&'a Node<T>>) {
+        while let Some(n) = node {
+            self.stack.push(n);
+            node = n.left.as_deref();
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+/// Iterates over a version's values within bounds in ascending order.
+pub struct Range<'a, T, R> {
+    // Only the stack is used; REMAINING is an upper bound.
+    iter: Iter<'a, T>,
+    bounds: R,
+}
+
+impl<'a, T: Ord, R: RangeBounds<T>> Iterator for Range<'a, T, R> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let value = self.iter.next()?;
+        let below_end = match self.bounds.end_bound() {
+            Bound::Included(n) => value <= n,
+            Bound::Excluded(n) => value < n,
+            Bound::Unbounded => true,
+        };
+
+        if below_end {
+            Some(value)
+        } else {
+            self.iter.stack.clear();
+            None
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Tree<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,79 @@
+use std::cmp::Ordering;
+
+/// A value whose first few bytes can be packed into a `u64` that orders
+/// the same way, so that most comparisons can skip the value itself.
+pub trait OrderPrefix: Ord {
+    /// Returns a number such that if A's prefix is less than B's, A is
+    /// less than B. Equal prefixes say nothing.
+    fn order_prefix(&self) -> u64;
+}
+
+// Packs up to the first 8 BYTES, padding short ones with zeros.
+fn byte_prefix(bytes: &[u8]) -> u64 {
+    let mut prefix = [0; 8];
+    let len = bytes.len().min(8);
+    prefix[..len].copy_from_slice(&bytes[..len]);
+    u64::from_be_bytes(prefix)
+}
+
+impl OrderPrefix for String {
+    fn order_prefix(&self) -> u64 {
+        byte_prefix(self.as_bytes())
+    }
+}
+
+impl OrderPrefix for Vec<u8> {
+    fn order_prefix(&self) -> u64 {
+        byte_prefix(self)
+    }
+}
+
+impl OrderPrefix for Box<str> {
+    fn order_prefix(&self) -> u64 {
+        byte_prefix(self.as_bytes())
+    }
+}
+
+/// A value stored with its order prefix. Comparisons check the prefixes
+/// first, and only compare the values when they are equal.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Prefixed<T> {
+    prefix: u64,
+    value: T,
+}
+
+impl<T: OrderPrefix> Prefixed<T> {
+    #[must_use]
+    pub fn new(value: T) -> Self {
+        Self {
+            prefix: value.order_prefix(),
+            value,
+        }
+    }
+}
+
+impl<T> Prefixed<T> {
+    #[must_use]
+    pub const fn value(&self) -> &T {
+        &self.value
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Ord> Ord for Prefixed<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.prefix
+            .cmp(&other.prefix)
+            .then_with(|| self.value.cmp(&other.value))
+    }
+}
+
+impl<T: Ord> PartialOrd for Prefixed<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,120 @@
+//! A double-ended priority queue built on `Tree`.
+
+use super::{Handle, Tree};
+
+/// A priority queue that pops from either end and can remove or change
+/// any value through the handle `push` returned, which a binary heap
+/// can't. Equal values are kept, and pop from the smaller end in the
+/// order they were pushed. Every operation takes O(log n).
+#[derive(Clone)]
+pub struct TreeQueue<T> {
+    tree: Tree<T>,
+}
+
+impl<T> Default for TreeQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TreeQueue<T> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { tree: Tree::new() }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.tree.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.tree.size == 0
+    }
+
+    /// Returns the value HANDLE refers to, or None if it was removed.
+    #[must_use]
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.tree.get_handle(handle)
+    }
+
+    /// Returns the smallest value without removing it.
+    #[must_use]
+    pub fn peek_min(&self) -> Option<&T> {
+        self.min_index().map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Returns the largest value without removing it.
+    #[must_use]
+    pub fn peek_max(&self) -> Option<&T> {
+        self.max_index().map(|n| &self.tree.slab[n].value)
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.tree.iter()
+    }
+
+    fn min_index(&self) -> Option<usize> {
+        (!self.is_empty()).then(|| self.tree.min_index(self.tree.root))
+    }
+
+    fn max_index(&self) -> Option<usize> {
+        (!self.is_empty()).then(|| self.tree.max_index(self.tree.root))
+    }
+}
+
+impl<T: Ord> TreeQueue<T> {
+    /// Adds VALUE and returns a handle to it.
+    pub fn push(&mut self, value: T) -> Handle {
+        let index = self.tree.insert_after_equal(value);
+        let Some(handle) = self.tree.handle(index) else {
+            unreachable!()
+        };
+        handle
+    }
+
+    /// Removes the smallest value and returns it.
+    pub fn pop_min(&mut self) -> Option<T> {
+        let index = self.min_index()?;
+        Some(self.tree.remove_index(index))
+    }
+
+    /// Removes the largest value and returns it.
+    pub fn pop_max(&mut self) -> Option<T> {
+        let index = self.max_index()?;
+        Some(self.tree.remove_index(index))
+    }
+
+    /// Removes the value HANDLE refers to and returns it, or None if it
+    /// was already removed.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        self.tree.get_handle(handle)?;
+        Some(self.tree.remove_index(handle.index()))
+    }
+
+    /// Replaces the value HANDLE refers to with VALUE, as for a decrease
+    /// key. Returns the handle to VALUE, which is HANDLE itself when VALUE
+    /// stays between the same neighbors, or VALUE back if HANDLE's value
+    /// was removed.
+    pub fn update(&mut self, handle: Handle, value: T) -> Result<Handle, T> {
+        if self.tree.get_handle(handle).is_none() {
+            return Err(value);
+        }
+
+        let index = handle.index();
+        let prev = self.tree.predecessor(index);
+        let next = self.tree.successor(index);
+        let slab = &self.tree.slab;
+        if prev.is_none_or(|n| slab[n].value <= value)
+            && next.is_none_or(|n| value <= slab[n].value)
+        {
+            self.tree.slab[index].value = value;
+            return Ok(handle);
+        }
+
+        self.tree.remove_index(index);
+        Ok(self.push(value))
+    }
+}
@@ -0,0 +1,194 @@
+//! A map from ranges of keys to values, built on `Tree`.
+
+use super::Tree;
+use std::cmp::{self, Ordering};
+use std::ops::Range;
+
+// Ordered by the range's start. The ranges never overlap, so no two
+// entries start at the same key.
+#[derive(Clone)]
+struct Entry<K, V> {
+    range: Range<K>,
+    value: V,
+}
+
+impl<K: Ord, V> PartialEq for Entry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, V> Eq for Entry<K, V> {}
+
+impl<K: Ord, V> PartialOrd for Entry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for Entry<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.range.start.cmp(&other.range.start)
+    }
+}
+
+/// A map from half-open ranges of keys to values, like a memory map or a
+/// table of address ranges. The ranges never overlap: inserting a range
+/// cuts the parts it covers out of the ranges already there. Ranges that
+/// touch and map to equal values are joined into one.
+#[derive(Clone)]
+pub struct RangeMap<K, V> {
+    tree: Tree<Entry<K, V>>,
+}
+
+impl<K, V> Default for RangeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> RangeMap<K, V> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { tree: Tree::new() }
+    }
+
+    /// Returns the number of ranges, after joining.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.tree.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.tree.size == 0
+    }
+}
+
+impl<K: Ord, V> RangeMap<K, V> {
+    /// Returns the value KEY maps to, if any.
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    /// Returns the range containing KEY and its value, if any.
+    #[must_use]
+    pub fn get_key_value(&self, key: &K) -> Option<(&Range<K>, &V)> {
+        let entry = &self.tree.slab[self.last_starting_before(key, true)?].value;
+        entry
+            .range
+            .contains(key)
+            .then_some((&entry.range, &entry.value))
+    }
+
+    /// Returns an iterator over the ranges and their values, ordered by
+    /// start.
+    pub fn iter(&self) -> impl Iterator<Item = (&Range<K>, &V)> {
+        self.tree.iter().map(|entry| (&entry.range, &entry.value))
+    }
+
+    // Returns the index of the range with the largest start before KEY,
+    // or at KEY if INCLUSIVE is true.
+    fn last_starting_before(&self, key: &K, inclusive: bool) -> Option<usize> {
+        let mut found = None;
+        let mut current = (!self.is_empty()).then_some(self.tree.root);
+        while let Some(n) = current {
+            let node = &self.tree.slab[n];
+            let start = &node.value.range.start;
+            if start < key || (inclusive && start == key) {
+                found = Some(n);
+                current = node.right();
+            } else {
+                current = node.left();
+            }
+        }
+        found
+    }
+
+    // Removes every range that overlaps or touches RANGE and returns
+    // them in order.
+    fn take_touching(&mut self, range: &Range<K>) -> Vec<Entry<K, V>> {
+        let first = match self.last_starting_before(&range.start, false) {
+            Some(n) if self.tree.slab[n].value.range.end >= range.start => Some(n),
+            Some(n) => self.tree.successor(n),
+            None => (!self.is_empty()).then(|| self.tree.min_index(self.tree.root)),
+        };
+
+        let touching: Vec<usize> = std::iter::successors(first, |&n| self.tree.successor(n))
+            .take_while(|&n| self.tree.slab[n].value.range.start <= range.end)
+            .collect();
+        // Removing only relinks nodes, so the other indices stay valid.
+        touching
+            .into_iter()
+            .map(|n| self.tree.remove_index(n))
+            .collect()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + PartialEq> RangeMap<K, V> {
+    /// Maps every key in RANGE to VALUE, replacing what they mapped to
+    /// before. Ranges that stick out of RANGE keep their other parts.
+    /// Empty ranges are ignored.
+    pub fn insert(&mut self, range: Range<K>, value: V) {
+        if range.is_empty() {
+            return;
+        }
+
+        let mut joined = range.clone();
+        for entry in self.take_touching(&range) {
+            if entry.value == value {
+                if entry.range.start < joined.start {
+                    joined.start = entry.range.start;
+                }
+                if entry.range.end > joined.end {
+                    joined.end = entry.range.end;
+                }
+            } else {
+                self.insert_outside(entry, &range);
+            }
+        }
+        self.tree.insert(Entry {
+            range: joined,
+            value,
+        });
+    }
+
+    /// Unmaps every key in RANGE. Ranges that stick out of RANGE keep
+    /// their other parts.
+    pub fn remove(&mut self, range: Range<K>) {
+        if range.is_empty() {
+            return;
+        }
+
+        for entry in self.take_touching(&range) {
+            self.insert_outside(entry, &range);
+        }
+    }
+
+    // Puts back the parts of ENTRY before and after RANGE, if any.
+    fn insert_outside(&mut self, entry: Entry<K, V>, range: &Range<K>) {
+        let Entry { range: old, value } = entry;
+        let before = (old.start < range.start)
+            .then(|| old.start.clone()..cmp::min(&old.end, &range.start).clone());
+        let after = (old.end > range.end)
+            .then(|| cmp::max(&old.start, &range.end).clone()..old.end.clone());
+
+        match (before, after) {
+            (Some(before), Some(after)) => {
+                self.tree.insert(Entry {
+                    range: before,
+                    value: value.clone(),
+                });
+                self.tree.insert(Entry {
+                    range: after,
+                    value,
+                });
+            }
+            (Some(part), None) | (None, Some(part)) => {
+                self.tree.insert(Entry { range: part, value });
+            }
+            (None, None) => (),
+        }
+    }
+}
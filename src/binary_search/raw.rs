@@ -0,0 +1,33 @@
+use super::SlotIndex;
+use std::cmp::Ordering;
+
+/// A node with a fixed layout, for reading a tree outside of the process
+/// that built it. Missing links are stored as `SlotIndex::NONE`. Use a
+/// fixed width index type rather than `usize` when the reader may differ
+/// in pointer width.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawNode<T, I> {
+    pub value: T,
+    pub left: I,
+    pub right: I,
+    pub height: i8,
+}
+
+impl<T: Ord, I: SlotIndex> RawNode<T, I> {
+    /// Returns the position of VALUE in NODES if it is found. NODES must
+    /// be laid out by `Tree::to_raw`, with the root first.
+    pub fn search(nodes: &[Self], value: &T) -> Option<usize> {
+        let mut index = I::from_usize(0);
+
+        while !nodes.is_empty() && index != I::NONE {
+            let node = &nodes[index.to_usize()];
+            index = match value.cmp(&node.value) {
+                Ordering::Less => node.left,
+                Ordering::Greater => node.right,
+                Ordering::Equal => return Some(index.to_usize()),
+            };
+        }
+        None
+    }
+}
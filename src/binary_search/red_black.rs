@@ -0,0 +1,251 @@
+use super::linked::{Linked, Node};
+use super::{InvariantError, SlotIndex};
+use std::mem;
+use std::ops::RangeBounds;
+
+/// A red-black tree stored in a `Vec`, like `Tree`. It's less tightly
+/// balanced, so lookups may visit a few more nodes, but inserting and
+/// removing rotate at most two and three times.
+#[derive(Clone)]
+pub struct RbTree<T, I = u32> {
+    // Each node's metadata is whether it's red.
+    links: Linked<T, I, bool>,
+}
+
+impl<T> Default for RbTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, I: SlotIndex> RbTree<T, I> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            links: Linked::new(),
+        }
+    }
+
+    /// Creates an empty tree with room for CAPACITY values.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            links: Linked::with_capacity(capacity),
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.links.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.links.size == 0
+    }
+
+    /// Returns a reference to the value at INDEX if it exists.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.links.get(index)
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.links.indices().map(|n| &self.links.slab[n].value)
+    }
+
+    fn is_red(&self, index: Option<usize>) -> bool {
+        index.is_some_and(|n| self.links.slab[n].meta)
+    }
+
+    fn set_red(&mut self, index: Option<usize>, red: bool) {
+        if let Some(n) = index {
+            self.links.slab[n].meta = red;
+        }
+    }
+
+    // Restores the red-black properties after the red node at INDEX was
+    // attached.
+    fn fix_insert(&mut self, mut index: usize) {
+        while let Some(parent) = self.links.slab[index]
+            .parent()
+            .filter(|&n| self.is_red(Some(n)))
+        {
+            // A red node is never the root, so it has a parent.
+            let Some(grandparent) = self.links.slab[parent].parent() else {
+                unreachable!()
+            };
+            let parent_is_left = self.links.slab[grandparent].left() == Some(parent);
+            let uncle = if parent_is_left {
+                self.links.slab[grandparent].right()
+            } else {
+                self.links.slab[grandparent].left()
+            };
+
+            if self.is_red(uncle) {
+                self.set_red(Some(parent), false);
+                self.set_red(uncle, false);
+                self.set_red(Some(grandparent), true);
+                index = grandparent;
+                continue;
+            }
+
+            let mut parent = parent;
+            if parent_is_left {
+                if self.links.slab[parent].right() == Some(index) {
+                    parent = self.links.rotate_left(parent);
+                }
+                self.links.rotate_right(grandparent);
+            } else {
+                if self.links.slab[parent].left() == Some(index) {
+                    parent = self.links.rotate_right(parent);
+                }
+                self.links.rotate_left(grandparent);
+            }
+            self.set_red(Some(parent), false);
+            self.set_red(Some(grandparent), true);
+            break;
+        }
+
+        let root = self.links.root;
+        self.links.slab[root].meta = false;
+    }
+
+    // Removes the node at INDEX and returns its value.
+    fn remove_index(&mut self, index: usize) -> T {
+        let unlinked = self.links.unlink(index);
+
+        // A moved successor takes the removed node's color, so its own
+        // color is the one that goes missing.
+        let red = self.links.slab[index].meta;
+        let removed_red = match unlinked.moved {
+            Some(n) => mem::replace(&mut self.links.slab[n].meta, red),
+            None => red,
+        };
+
+        if !removed_red && !self.is_empty() {
+            self.fix_remove(unlinked.child, unlinked.child_parent);
+        }
+        self.links.slab.remove(index).value
+    }
+
+    // Restores the red-black properties after a black node was unlinked
+    // from above INDEX, whose parent is PARENT.
+    fn fix_remove(&mut self, mut index: Option<usize>, mut parent: Option<usize>) {
+        while let (false, Some(p)) = (self.is_red(index), parent) {
+            // The missing black node means the sibling's side has one,
+            // so the sibling exists.
+            let is_left = self.links.slab[p].left() == index;
+            let sibling = |tree: &Self| {
+                let node = &tree.links.slab[p];
+                let sibling = if is_left { node.right() } else { node.left() };
+                sibling.unwrap_or_else(|| unreachable!())
+            };
+
+            let mut s = sibling(self);
+            if self.links.slab[s].meta {
+                self.links.slab[s].meta = false;
+                self.links.slab[p].meta = true;
+                self.rotate_toward(p, is_left);
+                s = sibling(self);
+            }
+
+            let (near, far) = self.children_from(s, is_left);
+            if !self.is_red(near) && !self.is_red(far) {
+                self.links.slab[s].meta = true;
+                index = Some(p);
+                parent = self.links.slab[p].parent();
+                continue;
+            }
+
+            if !self.is_red(far) {
+                self.set_red(near, false);
+                self.links.slab[s].meta = true;
+                self.rotate_toward(s, !is_left);
+                s = sibling(self);
+            }
+
+            self.links.slab[s].meta = self.links.slab[p].meta;
+            self.links.slab[p].meta = false;
+            let (_, far) = self.children_from(s, is_left);
+            self.set_red(far, false);
+            self.rotate_toward(p, is_left);
+            index = Some(self.links.root);
+            break;
+        }
+
+        self.set_red(index, false);
+    }
+
+    // Rotates INDEX down to the left if LEFT is true, otherwise down to
+    // the right.
+    fn rotate_toward(&mut self, index: usize, left: bool) {
+        if left {
+            self.links.rotate_left(index);
+        } else {
+            self.links.rotate_right(index);
+        }
+    }
+
+    // Returns the children of INDEX, nearest to the left first if LEFT is
+    // true, otherwise nearest to the right first.
+    fn children_from(&self, index: usize, left: bool) -> (Option<usize>, Option<usize>) {
+        let node_data = &self.links.slab[index];
+        if left {
+            (node_data.left(), node_data.right())
+        } else {
+            (node_data.right(), node_data.left())
+        }
+    }
+}
+
+impl<T: Ord, I: SlotIndex> RbTree<T, I> {
+    /// Returns the index of VALUE if it is found.
+    pub fn contains(&self, value: T) -> Option<usize> {
+        self.links.search(&value).ok()
+    }
+
+    /// Returns an iterator over the values within BOUNDS in ascending
+    /// order. Finding the first takes O(log n).
+    pub fn range<R: RangeBounds<T>>(&self, bounds: R) -> impl Iterator<Item = &T> {
+        self.links.range(bounds)
+    }
+
+    /// Inserts VALUE, returning its index, or None if it's already in
+    /// the tree.
+    pub fn insert(&mut self, value: T) -> Option<usize> {
+        let (parent, ordering) = self.links.search(&value).err()?;
+        let index = self.links.attach(parent, ordering, Node::new(value, true));
+        self.fix_insert(index);
+        Some(index)
+    }
+
+    /// Removes VALUE from the tree and returns it, if it was there.
+    pub fn remove(&mut self, value: T) -> Option<T> {
+        let index = self.links.search(&value).ok()?;
+        Some(self.remove_index(index))
+    }
+
+    /// Checks that the tree is ordered, that no red node has a red child,
+    /// that every path down has as many black nodes, and that every node
+    /// is reachable from the root and linked to its parent. Black height
+    /// mismatches are reported as `Height` and red children as `Balance`.
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        let root = self.links.root;
+        if !self.is_empty() && self.links.slab.get(root).is_some_and(|n| n.meta) {
+            return Err(InvariantError::Balance(root));
+        }
+
+        // Each subtree returns its black height.
+        self.links.validate(0, |index, node_data, left, right| {
+            if node_data.meta && (self.is_red(node_data.left()) || self.is_red(node_data.right())) {
+                return Err(InvariantError::Balance(index));
+            }
+            if left != right {
+                return Err(InvariantError::Height(index));
+            }
+            Ok(left + usize::from(!node_data.meta))
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use super::{Augment, SlotIndex, Tree};
+use std::fmt::{self, Display, Write};
+
+// Where a node hangs from its parent.
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Root,
+    Right,
+    Left,
+}
+
+impl<T: Display, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Returns a drawing of the tree on its side, one value per line, with
+    /// the root on the left and larger values above smaller ones.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        if !self.is_empty() {
+            match self.render_subtree(self.root, "", Side::Root, &mut text) {
+                Ok(()) => {}
+                // Writing to a String never fails.
+                Err(_) => unreachable!(),
+            }
+        }
+        text
+    }
+
+    // Draws the subtree at INDEX, which hangs from SIDE of its parent,
+    // with every line after PREFIX.
+    fn render_subtree(
+        &self,
+        index: usize,
+        prefix: &str,
+        side: Side,
+        text: &mut String,
+    ) -> fmt::Result {
+        let node_data = &self.slab[index];
+        // A branch continues past a child when the parent's line is on
+        // the child's other side.
+        let beside = |child_side| {
+            let branch = if side == child_side { "|   " } else { "    " };
+            if side == Side::Root {
+                String::new()
+            } else {
+                format!("{prefix}{branch}")
+            }
+        };
+
+        if let Some(n) = node_data.right() {
+            self.render_subtree(n, &beside(Side::Left), Side::Right, text)?;
+        }
+        let branch = match side {
+            Side::Root => "",
+            Side::Right => "/-- ",
+            Side::Left => "\\-- ",
+        };
+        writeln!(text, "{prefix}{branch}{}", node_data.value)?;
+        if let Some(n) = node_data.left() {
+            self.render_subtree(n, &beside(Side::Right), Side::Left, text)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,384 @@
+use super::slab::Slab;
+use super::{ordered, InvariantError, SlotIndex};
+use std::cmp::Ordering;
+use std::ops::RangeBounds;
+
+// Only the links to the children are kept. Missing links are `I::NONE`,
+// which keeps them as narrow as the index type.
+#[derive(Clone)]
+struct Node<T, I> {
+    value: T,
+    left: I,
+    right: I,
+}
+
+impl<T, I: SlotIndex> Node<T, I> {
+    const fn new(value: T) -> Self {
+        Self {
+            value,
+            left: I::NONE,
+            right: I::NONE,
+        }
+    }
+
+    fn left(&self) -> Option<usize> {
+        (self.left != I::NONE).then(|| self.left.to_usize())
+    }
+
+    fn right(&self) -> Option<usize> {
+        (self.right != I::NONE).then(|| self.right.to_usize())
+    }
+
+    fn set_left(&mut self, link: Option<usize>) {
+        self.left = link.map_or(I::NONE, I::from_usize);
+    }
+
+    fn set_right(&mut self, link: Option<usize>) {
+        self.right = link.map_or(I::NONE, I::from_usize);
+    }
+}
+
+// Returns the deepest a node may be in a tree of LEN values, which is
+// log base 3/2 of LEN.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn max_depth(len: usize) -> usize {
+    (len as f64).log(1.5) as usize
+}
+
+/// A scapegoat tree stored in a `Vec`, like `Tree`. Nodes only hold their
+/// value and two links, with no height or parent, so it uses the least
+/// memory per value. Instead of rotating, it rebuilds a subtree when an
+/// insertion ends up too deep, or the whole tree when enough values were
+/// removed, which costs O(log n) amortized.
+#[derive(Clone)]
+pub struct ScapegoatTree<T, I = u32> {
+    slab: Slab<Node<T, I>, I>,
+    root: usize,
+    size: usize,
+    // The largest the tree has been since it was last fully rebuilt.
+    max_size: usize,
+}
+
+impl<T> Default for ScapegoatTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, I: SlotIndex> ScapegoatTree<T, I> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            slab: Slab::new(),
+            root: 0,
+            size: 0,
+            max_size: 0,
+        }
+    }
+
+    /// Creates an empty tree with room for CAPACITY values.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slab: Slab::with_capacity(capacity),
+            ..Self::new()
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Returns a reference to the value at INDEX if it exists.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.slab.get(index).map(|n| &n.value)
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let root = (!self.is_empty()).then_some(self.root);
+        self.indices(root).map(|n| &self.slab[n].value)
+    }
+
+    // Returns an iterator over the indices of the subtree at ROOT in
+    // ascending order of values.
+    fn indices(&self, root: Option<usize>) -> impl Iterator<Item = usize> + '_ {
+        self.indices_from(Vec::new(), root)
+    }
+
+    // Returns an iterator over the indices in ascending order of values,
+    // starting from STACK, the ancestors still to visit with the next on
+    // top, and then NEXT's subtree.
+    fn indices_from(
+        &self,
+        mut stack: Vec<usize>,
+        mut next: Option<usize>,
+    ) -> impl Iterator<Item = usize> + '_ {
+        std::iter::from_fn(move || {
+            while let Some(n) = next {
+                stack.push(n);
+                next = self.slab[n].left();
+            }
+            let index = stack.pop()?;
+            next = self.slab[index].right();
+            Some(index)
+        })
+    }
+
+    // Points PARENT's link to OLD at NEW instead. Without a parent, NEW
+    // becomes the root.
+    fn replace_child(&mut self, parent: Option<usize>, old: usize, new: Option<usize>) {
+        match parent {
+            Some(n) if self.slab[n].left() == Some(old) => self.slab[n].set_left(new),
+            Some(n) => self.slab[n].set_right(new),
+            None => {
+                if let Some(new) = new {
+                    self.root = new;
+                }
+            }
+        }
+    }
+
+    // Relinks the LEN nodes of the subtree at INDEX into a perfectly
+    // balanced shape. Returns its new root.
+    fn rebuild(&mut self, index: usize, len: usize) -> usize {
+        let mut order = Vec::with_capacity(len);
+        order.extend(self.indices(Some(index)));
+
+        let Some(root) = self.build_range(&order) else {
+            unreachable!()
+        };
+        root
+    }
+
+    // Links the nodes at ORDER, which are in ascending order of values,
+    // into a balanced subtree. Returns its root.
+    fn build_range(&mut self, order: &[usize]) -> Option<usize> {
+        if order.is_empty() {
+            return None;
+        }
+
+        let mid = order.len() / 2;
+        let left = self.build_range(&order[..mid]);
+        let right = self.build_range(&order[mid + 1..]);
+        let node_data = &mut self.slab[order[mid]];
+        node_data.set_left(left);
+        node_data.set_right(right);
+        Some(order[mid])
+    }
+
+    // The node at INDEX was attached below PATH, the indices from the
+    // root down, too deep for the tree's length. Rebuilds the subtree at
+    // the lowest ancestor whose child on the path is too heavy for it.
+    fn rebuild_scapegoat(&mut self, path: &[usize], index: usize) {
+        let (mut child, mut child_len) = (index, 1);
+
+        for (depth, &ancestor) in path.iter().enumerate().rev() {
+            let node_data = &self.slab[ancestor];
+            let sibling = if node_data.left() == Some(child) {
+                node_data.right()
+            } else {
+                node_data.left()
+            };
+            let len = child_len + 1 + self.indices(sibling).count();
+
+            // The child holds more than 2/3 of the values.
+            if 3 * child_len > 2 * len {
+                let parent = depth.checked_sub(1).map(|n| path[n]);
+                let root = self.rebuild(ancestor, len);
+                self.replace_child(parent, ancestor, Some(root));
+                return;
+            }
+            (child, child_len) = (ancestor, len);
+        }
+    }
+}
+
+impl<T: Ord, I: SlotIndex> ScapegoatTree<T, I> {
+    /// Returns the index of VALUE if it is found.
+    pub fn contains(&self, value: T) -> Option<usize> {
+        let mut current = (!self.is_empty()).then_some(self.root);
+        while let Some(n) = current {
+            let node_data = &self.slab[n];
+            current = match value.cmp(&node_data.value) {
+                Ordering::Less => node_data.left(),
+                Ordering::Greater => node_data.right(),
+                Ordering::Equal => return Some(n),
+            };
+        }
+        None
+    }
+
+    /// Returns an iterator over the values within BOUNDS in ascending
+    /// order. Finding the first takes O(log n).
+    pub fn range<R: RangeBounds<T>>(&self, bounds: R) -> impl Iterator<Item = &T> {
+        // Without parent links, the values within the lower bound on the
+        // way down are kept to come back up to.
+        let mut stack = Vec::new();
+        let mut current = (!self.is_empty()).then_some(self.root);
+        while let Some(n) = current {
+            let node_data = &self.slab[n];
+            if ordered::above_lower(&node_data.value, bounds.start_bound()) {
+                stack.push(n);
+                current = node_data.left();
+            } else {
+                current = node_data.right();
+            }
+        }
+
+        self.indices_from(stack, None)
+            .map(|n| &self.slab[n].value)
+            .take_while(move |&value| ordered::below_upper(value, bounds.end_bound()))
+    }
+
+    /// Inserts VALUE, returning its index, or None if it's already in
+    /// the tree.
+    pub fn insert(&mut self, value: T) -> Option<usize> {
+        // Without parent links, the way down is kept to find a scapegoat.
+        let mut path = Vec::new();
+        let mut ordering = Ordering::Equal;
+        let mut current = (!self.is_empty()).then_some(self.root);
+        while let Some(n) = current {
+            let node_data = &self.slab[n];
+            ordering = value.cmp(&node_data.value);
+            current = match ordering {
+                Ordering::Less => node_data.left(),
+                Ordering::Greater => node_data.right(),
+                Ordering::Equal => return None,
+            };
+            path.push(n);
+        }
+
+        let index = self.slab.insert(Node::new(value));
+        match path.last() {
+            None => self.root = index,
+            Some(&n) if ordering == Ordering::Less => self.slab[n].set_left(Some(index)),
+            Some(&n) => self.slab[n].set_right(Some(index)),
+        }
+        self.size += 1;
+        self.max_size = self.max_size.max(self.size);
+
+        if path.len() > max_depth(self.size) {
+            self.rebuild_scapegoat(&path, index);
+        }
+        Some(index)
+    }
+
+    /// Removes VALUE from the tree and returns it, if it was there.
+    pub fn remove(&mut self, value: T) -> Option<T> {
+        let mut parent = None;
+        let mut index = (!self.is_empty()).then_some(self.root)?;
+        loop {
+            let node_data = &self.slab[index];
+            let next = match value.cmp(&node_data.value) {
+                Ordering::Less => node_data.left(),
+                Ordering::Greater => node_data.right(),
+                Ordering::Equal => break,
+            };
+            parent = Some(index);
+            index = next?;
+        }
+
+        let node_data = &self.slab[index];
+        let replacement = match (node_data.left(), node_data.right()) {
+            (None, child) | (child, None) => child,
+            // The successor takes the removed node's place.
+            (Some(left), Some(right)) => {
+                let (mut successor_parent, mut successor) = (index, right);
+                while let Some(n) = self.slab[successor].left() {
+                    (successor_parent, successor) = (successor, n);
+                }
+                if successor_parent != index {
+                    let child = self.slab[successor].right();
+                    self.slab[successor_parent].set_left(child);
+                    self.slab[successor].set_right(Some(right));
+                }
+                self.slab[successor].set_left(Some(left));
+                Some(successor)
+            }
+        };
+        self.replace_child(parent, index, replacement);
+        self.size -= 1;
+
+        // Once a third of the values are gone, the depth bound could be
+        // broken, so the whole tree is rebuilt.
+        if 3 * self.size < 2 * self.max_size {
+            if !self.is_empty() {
+                self.root = self.rebuild(self.root, self.size);
+            }
+            self.max_size = self.size;
+        }
+        Some(self.slab.remove(index).value)
+    }
+
+    /// Checks that the tree is ordered, that no node is deeper than the
+    /// tree's length allows, and that every node is reachable from the
+    /// root. Nodes that are too deep are reported as `Height`, and nodes
+    /// linked twice as `Parent`.
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        let mut visited = vec![false; self.slab.len()];
+        if !self.is_empty() {
+            let max_depth = max_depth(self.max_size) + 1;
+            self.validate_subtree(self.root, (None, None), &mut visited, 0, max_depth)?;
+        }
+
+        for (index, &visited) in visited.iter().enumerate() {
+            if !visited && self.slab.get(index).is_some() {
+                return Err(InvariantError::Unreachable(index));
+            }
+        }
+        if visited.iter().filter(|&&n| n).count() != self.size {
+            return Err(InvariantError::Size);
+        }
+
+        match self.slab.find_free_list_error() {
+            Some(n) => Err(InvariantError::FreeList(n)),
+            None => Ok(()),
+        }
+    }
+
+    // Checks the subtree at INDEX, whose values must be between BOUNDS.
+    fn validate_subtree(
+        &self,
+        index: usize,
+        bounds: (Option<&T>, Option<&T>),
+        visited: &mut [bool],
+        depth: usize,
+        max_depth: usize,
+    ) -> Result<(), InvariantError> {
+        let Some(node_data) = self.slab.get(index) else {
+            return Err(InvariantError::Vacant(index));
+        };
+        if visited[index] {
+            return Err(InvariantError::Parent(index));
+        }
+        visited[index] = true;
+
+        let value = &node_data.value;
+        if bounds.0.is_some_and(|n| value <= n) || bounds.1.is_some_and(|n| value >= n) {
+            return Err(InvariantError::Order(index));
+        }
+        if depth > max_depth {
+            return Err(InvariantError::Height(index));
+        }
+
+        if let Some(n) = node_data.left() {
+            self.validate_subtree(n, (bounds.0, Some(value)), visited, depth + 1, max_depth)?;
+        }
+        if let Some(n) = node_data.right() {
+            self.validate_subtree(n, (Some(value), bounds.1), visited, depth + 1, max_depth)?;
+        }
+        Ok(())
+    }
+}
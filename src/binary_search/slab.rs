@@ -0,0 +1,449 @@
+use super::SlotIndex;
+use std::mem;
+use std::ops::{Index, IndexMut};
+
+#[derive(Clone)]
+enum Slot<N, I> {
+    Occupied(N),
+    // Vacant slots link to each other, forming the free list. Links go
+    // both ways so that any vacant slot can be unlinked in O(1).
+    Vacant { prev: Option<I>, next: Option<I> },
+}
+
+/// Nodes stored in a `Vec`. Indices of removed nodes are reused by later
+/// insertions before the `Vec` grows. Every slot that is inserted into,
+/// removed from, or mutably borrowed is marked as dirty. Every slot also
+/// counts how many times its node was removed, called its generation.
+#[derive(Clone)]
+pub(crate) struct Slab<N, I> {
+    slots: Vec<Slot<N, I>>,
+    next_free: Option<I>,
+    // One bit per slot.
+    dirty: Vec<u64>,
+    // Kept for slots that were trimmed, so that their generations don't
+    // start over when they're pushed again. Missing entries are 0.
+    generations: Vec<u32>,
+}
+
+impl<N, I: SlotIndex> Slab<N, I> {
+    pub const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            next_free: None,
+            dirty: Vec::new(),
+            generations: Vec::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: Vec::with_capacity(capacity),
+            next_free: None,
+            dirty: Vec::new(),
+            generations: Vec::new(),
+        }
+    }
+
+    // Returns the number of slots that fit without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    // Returns the bytes of allocated slots that don't hold one of the
+    // OCCUPIED nodes, whether vacant or never used.
+    pub fn wasted_bytes(&self, occupied: usize) -> usize {
+        (self.slots.capacity() - occupied) * mem::size_of::<Slot<N, I>>()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.slots.reserve(additional);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.slots.shrink_to_fit();
+        self.dirty.shrink_to_fit();
+    }
+
+    // Each node's position in NODES becomes its index.
+    pub fn from_vec(nodes: Vec<N>) -> Self {
+        assert!(nodes.len() <= I::MAX, "tree capacity exceeded");
+
+        let mut slab = Self {
+            slots: nodes.into_iter().map(Slot::Occupied).collect(),
+            next_free: None,
+            dirty: Vec::new(),
+            generations: Vec::new(),
+        };
+        slab.mark_range(0, slab.slots.len());
+        slab
+    }
+
+    // Returns the same slab with indices of type J. CONVERT is called on
+    // every node so that its links can be converted too.
+    pub fn convert<M, J: SlotIndex, F>(self, mut convert: F) -> Slab<M, J>
+    where
+        F: FnMut(N) -> M,
+    {
+        assert!(self.slots.len() <= J::MAX, "tree capacity exceeded");
+
+        let convert_link = |link: Option<I>| link.map(|n| J::from_usize(n.to_usize()));
+        Slab {
+            slots: self
+                .slots
+                .into_iter()
+                .map(|slot| match slot {
+                    Slot::Occupied(node) => Slot::Occupied(convert(node)),
+                    Slot::Vacant { prev, next } => Slot::Vacant {
+                        prev: convert_link(prev),
+                        next: convert_link(next),
+                    },
+                })
+                .collect(),
+            next_free: convert_link(self.next_free),
+            dirty: self.dirty,
+            generations: self.generations,
+        }
+    }
+
+    // Returns the number of slots, whether they are occupied or not.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    // Returns None if INDEX is out of range or vacant.
+    pub fn get(&self, index: usize) -> Option<&N> {
+        match self.slots.get(index)? {
+            Slot::Occupied(node) => Some(node),
+            Slot::Vacant { .. } => None,
+        }
+    }
+
+    // INDEX must be in range and occupied.
+    pub unsafe fn get_unchecked(&self, index: usize) -> &N {
+        // SAFETY: the caller guarantees that INDEX is in range.
+        match unsafe { self.slots.get_unchecked(index) } {
+            Slot::Occupied(node) => node,
+            // SAFETY: the caller guarantees that INDEX is occupied.
+            Slot::Vacant { .. } => unsafe { std::hint::unreachable_unchecked() },
+        }
+    }
+
+    // Returns the nodes at INDICES, which must be distinct and occupied.
+    pub fn get_disjoint_mut<const K: usize>(&mut self, indices: [usize; K]) -> [&mut N; K] {
+        for index in indices {
+            self.mark(index);
+        }
+        match self.slots.get_disjoint_mut(indices) {
+            Ok(slots) => slots.map(|slot| match slot {
+                Slot::Occupied(node) => node,
+                Slot::Vacant { .. } => panic!("slot is vacant"),
+            }),
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    // Returns an iterator yielding every slot's node in index order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = Option<&mut N>> {
+        self.mark_range(0, self.slots.len());
+        self.slots.iter_mut().map(|slot| match slot {
+            Slot::Occupied(node) => Some(node),
+            Slot::Vacant { .. } => None,
+        })
+    }
+
+    // Tries to use a vacant slot, otherwise pushes.
+    // Returns the index that was used.
+    pub fn insert(&mut self, node: N) -> usize {
+        match self.next_free {
+            Some(n) => {
+                let index = n.to_usize();
+                self.unlink(index);
+                self.slots[index] = Slot::Occupied(node);
+                self.mark(index);
+                index
+            }
+            None => self.push(node),
+        }
+    }
+
+    // Stores NODE in a new slot at the end, even if others are vacant.
+    pub fn push(&mut self, node: N) -> usize {
+        assert!(self.slots.len() < I::MAX, "tree capacity exceeded");
+        self.slots.push(Slot::Occupied(node));
+        self.mark(self.slots.len() - 1);
+        self.slots.len() - 1
+    }
+
+    // Vacates the slot at INDEX and returns its node.
+    pub fn remove(&mut self, index: usize) -> N {
+        let slot = Slot::Vacant {
+            prev: None,
+            next: self.next_free,
+        };
+        match mem::replace(&mut self.slots[index], slot) {
+            Slot::Occupied(node) => {
+                if let Some(n) = self.next_free {
+                    self.set_prev(n.to_usize(), Some(index));
+                }
+                self.next_free = Some(I::from_usize(index));
+                self.mark(index);
+                self.bump_generation(index);
+                node
+            }
+            Slot::Vacant { .. } => panic!("slot {index} is vacant"),
+        }
+    }
+
+    // Puts NODE back in the vacant slot at INDEX with the GENERATION it
+    // had before it was removed, as if it had never been vacated. Trimmed
+    // slots up to INDEX are added back first.
+    pub fn restore(&mut self, index: usize, node: N, generation: u32) {
+        while self.slots.len() <= index {
+            let vacant = self.slots.len();
+            self.slots.push(Slot::Vacant {
+                prev: None,
+                next: self.next_free,
+            });
+            if let Some(n) = self.next_free {
+                self.set_prev(n.to_usize(), Some(vacant));
+            }
+            self.next_free = Some(I::from_usize(vacant));
+        }
+
+        self.unlink(index);
+        self.slots[index] = Slot::Occupied(node);
+        self.mark(index);
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+        }
+        self.generations[index] = generation;
+    }
+
+    // Removes vacant slots from the end of the Vec, and unlinks
+    // them from the free list.
+    pub fn trim(&mut self) {
+        self.trim_to(0);
+    }
+
+    // Like `trim`, but keeps at least LEN slots.
+    pub fn trim_to(&mut self, len: usize) {
+        while self.slots.len() > len {
+            let Some(Slot::Vacant { .. }) = self.slots.last() else {
+                break;
+            };
+            self.unlink(self.slots.len() - 1);
+            self.slots.pop();
+        }
+    }
+
+    // Removes the vacant slot at INDEX from the free list.
+    fn unlink(&mut self, index: usize) {
+        let Slot::Vacant { prev, next } = self.slots[index] else {
+            unreachable!()
+        };
+
+        match prev {
+            Some(n) => self.set_next(n.to_usize(), next.map(I::to_usize)),
+            None => self.next_free = next,
+        }
+        if let Some(n) = next {
+            self.set_prev(n.to_usize(), prev.map(I::to_usize));
+        }
+    }
+
+    fn set_prev(&mut self, index: usize, link: Option<usize>) {
+        if let Slot::Vacant { prev, .. } = &mut self.slots[index] {
+            *prev = link.map(I::from_usize);
+        }
+    }
+
+    fn set_next(&mut self, index: usize, link: Option<usize>) {
+        if let Slot::Vacant { next, .. } = &mut self.slots[index] {
+            *next = link.map(I::from_usize);
+        }
+    }
+
+    // Moves OTHER's slots to the end of this slab. SHIFT is called on
+    // every moved node with the offset that was added to its index, so
+    // that its links can be updated. Returns the offset.
+    pub fn append<F: FnMut(&mut N, usize)>(&mut self, other: Self, mut shift: F) -> usize {
+        let offset = self.slots.len();
+        assert!(
+            offset + other.slots.len() <= I::MAX,
+            "tree capacity exceeded"
+        );
+
+        let shift_link = |link: Option<I>| link.map(|n| I::from_usize(n.to_usize() + offset));
+        self.slots
+            .extend(other.slots.into_iter().map(|slot| match slot {
+                Slot::Occupied(mut node) => {
+                    shift(&mut node, offset);
+                    Slot::Occupied(node)
+                }
+                Slot::Vacant { prev, next } => Slot::Vacant {
+                    prev: shift_link(prev),
+                    next: shift_link(next),
+                },
+            }));
+
+        // OTHER's free list is put in front of this one.
+        if let Some(head) = shift_link(other.next_free) {
+            let mut tail = head.to_usize();
+            while let Slot::Vacant { next: Some(n), .. } = self.slots[tail] {
+                tail = n.to_usize();
+            }
+
+            if let Some(n) = self.next_free {
+                self.set_next(tail, Some(n.to_usize()));
+                self.set_prev(n.to_usize(), Some(tail));
+            }
+            self.next_free = Some(head);
+        }
+
+        self.mark_range(offset, self.slots.len());
+        offset
+    }
+
+    // Moves the nodes at the indices in ORDER to the front, in that order,
+    // and drops every other slot. RELINK is called on every moved node with
+    // the table from old to new indices, so that its links can be updated.
+    // Returns the table.
+    pub fn compact<F>(&mut self, order: &[usize], mut relink: F) -> Vec<Option<usize>>
+    where
+        F: FnMut(&mut N, &[Option<usize>]),
+    {
+        let mut remap = vec![None; self.slots.len()];
+        for (new_index, &old_index) in order.iter().enumerate() {
+            remap[old_index] = Some(new_index);
+        }
+
+        let mut slots: Vec<_> = mem::take(&mut self.slots).into_iter().map(Some).collect();
+        self.slots = order
+            .iter()
+            .map(|&old_index| match slots[old_index].take() {
+                Some(Slot::Occupied(mut node)) => {
+                    relink(&mut node, &remap);
+                    Slot::Occupied(node)
+                }
+                _ => panic!("slot {old_index} is vacant"),
+            })
+            .collect();
+        self.next_free = None;
+
+        // Every slot that now holds a node may have held another one, and
+        // every dropped slot may hold one once it's pushed again.
+        for index in 0..remap.len() {
+            self.bump_generation(index);
+        }
+        self.mark_range(0, remap.len());
+        remap
+    }
+
+    // Returns the index of the first vacant slot that is linked wrongly or
+    // missing from the free list, if any.
+    pub fn find_free_list_error(&self) -> Option<usize> {
+        let mut linked = vec![false; self.slots.len()];
+        let mut prev_index = None;
+        let mut current = self.next_free;
+
+        while let Some(n) = current {
+            let index = n.to_usize();
+            let Some(Slot::Vacant { prev, next }) = self.slots.get(index) else {
+                return Some(index);
+            };
+            if linked[index] || prev.map(I::to_usize) != prev_index {
+                return Some(index);
+            }
+
+            linked[index] = true;
+            prev_index = Some(index);
+            current = *next;
+        }
+
+        self.slots
+            .iter()
+            .zip(linked)
+            .position(|(slot, linked)| matches!(slot, Slot::Vacant { .. }) && !linked)
+    }
+
+    pub fn generation(&self, index: usize) -> u32 {
+        self.generations.get(index).copied().unwrap_or(0)
+    }
+
+    // Returns the generation of every slot, including those that were
+    // never removed from and so have no entry yet.
+    pub fn generations(&self) -> Vec<u32> {
+        let len = self.slots.len().max(self.generations.len());
+        (0..len).map(|index| self.generation(index)).collect()
+    }
+
+    // Raises the generation of every slot above both its own and the one
+    // in GENERATIONS, taken from the slab this one replaces, so that
+    // handles to nodes of either slab don't resolve in it.
+    pub fn supersede(&mut self, generations: &[u32]) {
+        let len = self.slots.len().max(generations.len());
+        if len > self.generations.len() {
+            self.generations.resize(len, 0);
+        }
+        for (index, generation) in self.generations.iter_mut().enumerate().take(len) {
+            let old = generations.get(index).copied().unwrap_or(0);
+            *generation = (*generation).max(old).wrapping_add(1);
+        }
+    }
+
+    fn bump_generation(&mut self, index: usize) {
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+        }
+        self.generations[index] = self.generations[index].wrapping_add(1);
+    }
+
+    fn mark(&mut self, index: usize) {
+        let word = index / 64;
+        if word >= self.dirty.len() {
+            self.dirty.resize(word + 1, 0);
+        }
+        self.dirty[word] |= 1 << (index % 64);
+    }
+
+    fn mark_range(&mut self, start: usize, end: usize) {
+        for index in start..end {
+            self.mark(index);
+        }
+    }
+
+    // Returns the dirty indices in ascending order. Indices past the end
+    // belonged to slots that were trimmed.
+    pub fn dirty(&self) -> impl Iterator<Item = usize> + '_ {
+        self.dirty.iter().enumerate().flat_map(|(word, &bits)| {
+            (0..64)
+                .filter(move |bit| bits & (1 << bit) != 0)
+                .map(move |bit| word * 64 + bit)
+        })
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+    }
+}
+
+impl<N, I: SlotIndex> Index<usize> for Slab<N, I> {
+    type Output = N;
+
+    fn index(&self, index: usize) -> &N {
+        match &self.slots[index] {
+            Slot::Occupied(node) => node,
+            Slot::Vacant { .. } => panic!("slot {index} is vacant"),
+        }
+    }
+}
+
+impl<N, I: SlotIndex> IndexMut<usize> for Slab<N, I> {
+    fn index_mut(&mut self, index: usize) -> &mut N {
+        self.mark(index);
+        match &mut self.slots[index] {
+            Slot::Occupied(node) => node,
+            Slot::Vacant { .. } => panic!("slot {index} is vacant"),
+        }
+    }
+}
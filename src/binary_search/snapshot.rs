@@ -0,0 +1,491 @@
+use super::ordered::{above_lower, below_upper};
+use super::{Augment, Counters, DuplicatePolicy, Iter, Node, ShrinkPolicy, Slab, SlotIndex, Tree};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeBounds;
+
+// Every snapshot starts with these bytes, then the format's version.
+const MAGIC: [u8; 4] = *b"AVLC";
+const VERSION: u32 = 1;
+// The root starts right after the header.
+const HEADER_LEN: usize = 18;
+
+// Bits of the byte before each value, saying which children follow it.
+const HAS_LEFT: u8 = 1;
+const HAS_RIGHT: u8 = 2;
+
+impl<T, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Returns a snapshot of the tree, which `from_bytes` loads without
+    /// inserting anything. ENCODE appends the bytes of a value to the
+    /// buffer it is given. Numbers in the format are little-endian.
+    ///
+    /// # Panics
+    ///
+    /// Panics if ENCODE appends more than `u32::MAX` bytes for a value.
+    #[must_use]
+    pub fn to_bytes<F: FnMut(&T, &mut Vec<u8>)>(&self, mut encode: F) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.size as u64).to_le_bytes());
+        bytes.push(match self.shrink_policy {
+            ShrinkPolicy::Never => 0,
+            ShrinkPolicy::OnDemand => 1,
+            ShrinkPolicy::Aggressive => 2,
+        });
+        bytes.push(match self.duplicate_policy {
+            DuplicatePolicy::Reject => 0,
+            DuplicatePolicy::ReplaceExisting => 1,
+            DuplicatePolicy::KeepBoth => 2,
+        });
+
+        // Nodes are written in pre-order, each saying which children it
+        // has, which is enough to link them back up. A node's left child
+        // follows it, and it stores where its right child starts, so the
+        // snapshot can be searched in place.
+        let mut stack: Vec<(usize, Option<usize>)> = (!self.is_empty())
+            .then_some((self.root, None))
+            .into_iter()
+            .collect();
+        while let Some((index, offset_at)) = stack.pop() {
+            if let Some(at) = offset_at {
+                let offset = bytes.len() as u64;
+                bytes[at..at + 8].copy_from_slice(&offset.to_le_bytes());
+            }
+
+            let node_data = &self.slab[index];
+            let mut flags = 0;
+            if node_data.left().is_some() {
+                flags |= HAS_LEFT;
+            }
+            if node_data.right().is_some() {
+                flags |= HAS_RIGHT;
+            }
+            bytes.push(flags);
+
+            // The value's length and the right child's offset are filled
+            // in once they're known.
+            let len_at = bytes.len();
+            bytes.extend_from_slice(&[0; 4]);
+            let right_at = node_data.right().map(|_| {
+                bytes.extend_from_slice(&[0; 8]);
+                len_at + 4
+            });
+            let start = bytes.len();
+            encode(&node_data.value, &mut bytes);
+            let len = u32::try_from(bytes.len() - start).expect("value is too long to encode");
+            bytes[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+
+            // Pushing the right child first visits the left one first.
+            if let Some(n) = node_data.right() {
+                stack.push((n, right_at));
+            }
+            if let Some(n) = node_data.left() {
+                stack.push((n, None));
+            }
+        }
+        bytes
+    }
+}
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Loads a tree from a snapshot made by `to_bytes`. DECODE turns the
+    /// bytes of a value back into it, or returns None if they're invalid.
+    /// The snapshot is checked to hold exactly a balanced, ordered tree of
+    /// its stated length, which takes O(n) but no insertions.
+    pub fn from_bytes<F>(bytes: &[u8], mut decode: F) -> Result<Self, SnapshotError>
+    where
+        F: FnMut(&[u8]) -> Option<T>,
+    {
+        let mut reader = Reader { bytes, offset: 0 };
+        let Header {
+            size,
+            shrink_policy,
+            duplicate_policy,
+        } = reader.header()?;
+        if size > I::MAX {
+            return Err(SnapshotError::Length);
+        }
+
+        // Every node takes at least 5 bytes, which bounds the allocation
+        // when the stated length is wrong.
+        let mut nodes: Vec<Node<T, I, A>> = Vec::with_capacity(size.min(reader.bytes.len() / 5));
+        reader.nodes(size, |position, link, bytes| {
+            let value = decode(bytes).ok_or(SnapshotError::Value(position))?;
+            let mut node = Node::new(value);
+            node.set_parent(link.map(|(parent, _)| parent));
+            nodes.push(node);
+            match link {
+                Some((parent, Side::Left)) => nodes[parent].set_left(Some(position)),
+                Some((parent, Side::Right)) => nodes[parent].set_right(Some(position)),
+                None => {}
+            }
+            Ok(())
+        })?;
+
+        let tree = Self {
+            slab: Slab::from_vec(nodes),
+            root: 0,
+            size,
+            shrink_policy,
+            duplicate_policy,
+            counters: Counters::new(),
+            max: None,
+        };
+        tree.check_snapshot()
+    }
+
+    // Fills in the heights and augments of a tree loaded by `from_bytes`,
+    // and checks that it's balanced and ordered.
+    fn check_snapshot(mut self) -> Result<Self, SnapshotError> {
+        // Nodes come after their parents in pre-order, so going backwards
+        // updates every child before its parent.
+        for index in (0..self.size).rev() {
+            if !(-1..=1).contains(&self.update_height(index)) {
+                return Err(SnapshotError::Balance(index));
+            }
+        }
+
+        let mut iter = Iter::new(&self);
+        let mut prev = iter.next_index();
+        while let (Some(p), Some(n)) = (prev, iter.next_index()) {
+            let ordered = match self.slab[p].value.cmp(&self.slab[n].value) {
+                Ordering::Less => true,
+                Ordering::Equal => self.duplicate_policy == DuplicatePolicy::KeepBoth,
+                Ordering::Greater => false,
+            };
+            if !ordered {
+                return Err(SnapshotError::Order(n));
+            }
+            prev = Some(n);
+        }
+        Ok(self)
+    }
+}
+
+/// A tree searched in place in the bytes of a snapshot made by
+/// `Tree::to_bytes`, such as a memory-mapped file, so there's nothing to
+/// load. DECODE turns the bytes of a value back into it, and can borrow
+/// from them. Lookups decode O(log n) values.
+///
+/// Creating a view checks that every node is where it should be, which
+/// takes O(n) but decodes nothing, so lookups can't fail. Values aren't
+/// checked to be in order; load the snapshot once with `Tree::from_bytes`
+/// to check that too.
+pub struct TreeView<'a, T> {
+    bytes: &'a [u8],
+    len: usize,
+    decode: fn(&'a [u8]) -> T,
+}
+
+// Derived impls would require T: Copy.
+impl<T> Clone for TreeView<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TreeView<'_, T> {}
+
+impl<'a, T> TreeView<'a, T> {
+    /// Returns a view of the snapshot in BYTES, after checking its header
+    /// and the links between its nodes as `Tree::from_bytes` does.
+    pub fn new(bytes: &'a [u8], decode: fn(&'a [u8]) -> T) -> Result<Self, SnapshotError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        let len = reader.header()?.size;
+        reader.nodes(len, |_, _, _| Ok(()))?;
+        Ok(Self { bytes, len, decode })
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn root(&self) -> Option<usize> {
+        (!self.is_empty()).then_some(HEADER_LEN)
+    }
+
+    // Reads the node starting at OFFSET. Returns its value and the offsets
+    // of its children.
+    fn node(&self, offset: usize) -> (T, Option<usize>, Option<usize>) {
+        let mut reader = Reader {
+            bytes: &self.bytes[offset..],
+            offset,
+        };
+        let read = |reader: &mut Reader<'a>| -> Result<_, SnapshotError> {
+            let flags = reader.byte()?;
+            let len = reader.u32()? as usize;
+            let right = if flags & HAS_RIGHT == 0 {
+                None
+            } else {
+                let offset = reader.u64()?;
+                Some(usize::try_from(offset).map_err(|_| SnapshotError::Truncated)?)
+            };
+            let value = reader.take(len)?;
+            let left = (flags & HAS_LEFT != 0).then_some(reader.offset);
+            Ok((value, left, right))
+        };
+        // `new` checked every node, so reading one can't fail.
+        let (value, left, right) = read(&mut reader).expect("snapshot was checked");
+        ((self.decode)(value), left, right)
+    }
+}
+
+impl<'a, T: Ord> TreeView<'a, T> {
+    /// Returns the stored value equal to VALUE, if any.
+    #[must_use]
+    pub fn get(&self, value: &T) -> Option<T> {
+        let mut current = self.root();
+        while let Some(offset) = current {
+            let (stored, left, right) = self.node(offset);
+            current = match value.cmp(&stored) {
+                Ordering::Less => left,
+                Ordering::Greater => right,
+                Ordering::Equal => return Some(stored),
+            };
+        }
+        None
+    }
+
+    #[must_use]
+    pub fn contains(&self, value: &T) -> bool {
+        self.get(value).is_some()
+    }
+
+    /// Returns an iterator over the values within RANGE, in ascending
+    /// order.
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> ViewRange<'a, T, R> {
+        let mut iter = ViewRange {
+            view: *self,
+            stack: Vec::new(),
+            range,
+        };
+
+        // Pushes every node within the lower bound on the path to the
+        // first value in range, leaving that one on top.
+        let mut current = self.root();
+        while let Some(offset) = current {
+            let (value, left, right) = self.node(offset);
+            if above_lower(&value, iter.range.start_bound()) {
+                iter.stack.push((value, right));
+                current = left;
+            } else {
+                current = right;
+            }
+        }
+        iter
+    }
+}
+
+/// An iterator over the values of a `TreeView` within a range, returned
+/// by `TreeView::range`.
+pub struct ViewRange<'a, T, R> {
+    view: TreeView<'a, T>,
+    // Values still to be returned, each with its right child's offset.
+    stack: Vec<(T, Option<usize>)>,
+    range: R,
+}
+
+impl<T: Ord, R: RangeBounds<T>> Iterator for ViewRange<'_, T, R> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let (value, right) = self.stack.pop()?;
+        if !below_upper(&value, self.range.end_bound()) {
+            self.stack.clear();
+            return None;
+        }
+
+        let mut current = right;
+        while let Some(offset) = current {
+            let (value, left, right) = self.view.node(offset);
+            self.stack.push((value, right));
+            current = left;
+        }
+        Some(value)
+    }
+}
+
+// Which child of its parent a node is.
+#[derive(Clone, Copy)]
+enum Side {
+    Left,
+    Right,
+}
+
+struct Header {
+    size: usize,
+    shrink_policy: ShrinkPolicy,
+    duplicate_policy: DuplicatePolicy,
+}
+
+// Reads a snapshot from the front. OFFSET counts the bytes read.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn header(&mut self) -> Result<Header, SnapshotError> {
+        if self.take(MAGIC.len())? != MAGIC {
+            return Err(SnapshotError::Magic);
+        }
+        let version = self.u32()?;
+        if version != VERSION {
+            return Err(SnapshotError::Version(version));
+        }
+        let size = usize::try_from(self.u64()?).map_err(|_| SnapshotError::Length)?;
+        let shrink_policy = match self.byte()? {
+            0 => ShrinkPolicy::Never,
+            1 => ShrinkPolicy::OnDemand,
+            2 => ShrinkPolicy::Aggressive,
+            _ => return Err(SnapshotError::Header),
+        };
+        let duplicate_policy = match self.byte()? {
+            0 => DuplicatePolicy::Reject,
+            1 => DuplicatePolicy::ReplaceExisting,
+            2 => DuplicatePolicy::KeepBoth,
+            _ => return Err(SnapshotError::Header),
+        };
+        Ok(Header {
+            size,
+            shrink_policy,
+            duplicate_policy,
+        })
+    }
+
+    // Reads the SIZE nodes after the header in pre-order, checking that
+    // each starts where its parent says and that nothing follows them.
+    // VISIT is called on each node's position, its parent's position and
+    // side, and the bytes of its value.
+    fn nodes<F>(&mut self, size: usize, mut visit: F) -> Result<(), SnapshotError>
+    where
+        F: FnMut(usize, Option<(usize, Side)>, &'a [u8]) -> Result<(), SnapshotError>,
+    {
+        // Links still waiting for a node, as the parent's position, the
+        // side and, for a right child, the offset the parent stored for
+        // it. The root has no parent.
+        let mut waiting: Vec<Option<(usize, Side, Option<u64>)>> =
+            if size == 0 { vec![] } else { vec![None] };
+        let mut position = 0;
+        while let Some(link) = waiting.pop() {
+            if position == size {
+                return Err(SnapshotError::Length);
+            }
+            if let Some((_, _, Some(offset))) = link {
+                if offset != self.offset as u64 {
+                    return Err(SnapshotError::Offset(position));
+                }
+            }
+
+            let flags = self.byte()?;
+            if flags & !(HAS_LEFT | HAS_RIGHT) != 0 {
+                return Err(SnapshotError::Flags(position));
+            }
+            let len = self.u32()? as usize;
+            let right_offset = if flags & HAS_RIGHT == 0 {
+                None
+            } else {
+                Some(self.u64()?)
+            };
+            let value = self.take(len)?;
+            visit(
+                position,
+                link.map(|(parent, side, _)| (parent, side)),
+                value,
+            )?;
+
+            if let Some(offset) = right_offset {
+                waiting.push(Some((position, Side::Right, Some(offset))));
+            }
+            if flags & HAS_LEFT != 0 {
+                waiting.push(Some((position, Side::Left, None)));
+            }
+            position += 1;
+        }
+        if position != size || !self.bytes.is_empty() {
+            return Err(SnapshotError::Length);
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.bytes.len() < len {
+            return Err(SnapshotError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        self.offset += len;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        let mut array = [0; 4];
+        array.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(array))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        let mut array = [0; 8];
+        array.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(array))
+    }
+}
+
+/// Returned by `Tree::from_bytes` and `TreeView::new` when the bytes
+/// aren't a valid snapshot.
+/// Positions count nodes in the order they're stored, from 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The bytes don't start like a snapshot.
+    Magic,
+    /// The snapshot is in another version of the format.
+    Version(u32),
+    /// The header holds an unknown policy.
+    Header,
+    /// The bytes end in the middle of the snapshot.
+    Truncated,
+    /// The number of nodes doesn't match the stated length, or bytes are
+    /// left after the last node.
+    Length,
+    /// The node at the position has unknown flags.
+    Flags(usize),
+    /// The node at the position isn't where its parent says it starts.
+    Offset(usize),
+    /// The value at the position couldn't be decoded.
+    Value(usize),
+    /// The heights of the node's children differ by more than one.
+    Balance(usize),
+    /// The value at the position is out of order.
+    Order(usize),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Magic => write!(f, "not a tree snapshot"),
+            Self::Version(v) => write!(f, "unsupported snapshot version {v}"),
+            Self::Header => write!(f, "snapshot header is invalid"),
+            Self::Truncated => write!(f, "snapshot is truncated"),
+            Self::Length => write!(f, "length doesn't match the number of nodes"),
+            Self::Flags(n) => write!(f, "node at position {n} has invalid flags"),
+            Self::Offset(n) => write!(f, "node at position {n} is at the wrong offset"),
+            Self::Value(n) => write!(f, "value at position {n} couldn't be decoded"),
+            Self::Balance(n) => write!(f, "node at position {n} is unbalanced"),
+            Self::Order(n) => write!(f, "value at position {n} is out of order"),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
@@ -0,0 +1,149 @@
+use super::{Count, Tree};
+use std::cmp::Ordering;
+use std::ops::{Bound, Index, RangeBounds};
+
+/// A list that keeps its values sorted, addressed by position like a
+/// `Vec`. Inserting, removing and finding a value by position all take
+/// O(log n), using a tree that counts the values in every subtree. Equal
+/// values are kept, in the order they were inserted.
+#[derive(Clone)]
+pub struct SortedList<T> {
+    tree: Tree<T, u32, Count>,
+}
+
+impl<T> Default for SortedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SortedList<T> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { tree: Tree::new() }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.tree.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.tree.size == 0
+    }
+
+    /// Returns the value at position RANK, counting from 0.
+    #[must_use]
+    pub fn get_by_rank(&self, rank: usize) -> Option<&T> {
+        self.tree.select(rank)
+    }
+
+    /// Removes the value at position RANK and returns it.
+    pub fn remove_by_rank(&mut self, rank: usize) -> Option<T> {
+        let index = self.tree.select_index(rank)?;
+        Some(self.tree.remove_index(index))
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.tree.iter()
+    }
+
+    /// Returns an iterator over the values at the positions in RANKS,
+    /// like slicing a `Vec`. Finding the first takes O(log n).
+    ///
+    /// # Panics
+    ///
+    /// Panics if RANKS starts after it ends or ends past the length.
+    pub fn range_by_rank<R: RangeBounds<usize>>(&self, ranks: R) -> impl Iterator<Item = &T> {
+        let start = match ranks.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match ranks.end_bound() {
+            Bound::Included(&n) => n + 1,
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(start <= end, "range starts at {start} but ends at {end}");
+        assert!(
+            end <= self.len(),
+            "range end {end} is out of range for length {}",
+            self.len()
+        );
+
+        let first = self.tree.select_index(start);
+        std::iter::successors(first, |&n| self.tree.successor(n))
+            .take(end - start)
+            .map(|n| &self.tree.slab[n].value)
+    }
+}
+
+impl<T: Ord> SortedList<T> {
+    /// Inserts VALUE after any equal values and returns its position.
+    pub fn insert(&mut self, value: T) -> usize {
+        let index = self.tree.insert_after_equal(value);
+        self.tree.rank_of_index(index)
+    }
+
+    /// Removes one value equal to VALUE and returns it, if there is one.
+    pub fn remove(&mut self, value: &T) -> Option<T> {
+        let rank = self.binary_search(value).ok()?;
+        self.remove_by_rank(rank)
+    }
+
+    /// Returns whether a value equal to VALUE is in the list.
+    #[must_use]
+    pub fn contains(&self, value: &T) -> bool {
+        self.binary_search(value).is_ok()
+    }
+
+    /// Searches for VALUE like `slice::binary_search`. Returns the
+    /// position of a value equal to it, or the position it would be
+    /// inserted at to keep the list sorted.
+    pub fn binary_search(&self, value: &T) -> Result<usize, usize> {
+        let count = |n: Option<usize>| n.map_or(0, |n| self.tree.slab[n].augment.0);
+        let mut rank = 0;
+        let mut current = (!self.is_empty()).then_some(self.tree.root);
+        while let Some(n) = current {
+            let node_data = &self.tree.slab[n];
+            current = match value.cmp(&node_data.value) {
+                Ordering::Less => node_data.left(),
+                Ordering::Equal => return Ok(rank + count(node_data.left())),
+                Ordering::Greater => {
+                    rank += count(node_data.left()) + 1;
+                    node_data.right()
+                }
+            };
+        }
+        Err(rank)
+    }
+}
+
+impl<T> Index<usize> for SortedList<T> {
+    type Output = T;
+
+    /// Returns the value at position RANK, like `get_by_rank`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the list has RANK or fewer values.
+    fn index(&self, rank: usize) -> &T {
+        match self.get_by_rank(rank) {
+            Some(value) => value,
+            None => panic!("rank {rank} is out of range for length {}", self.len()),
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SortedList<T> {
+    fn from_iter<V: IntoIterator<Item = T>>(values: V) -> Self {
+        let mut list = Self::new();
+        for value in values {
+            list.insert(value);
+        }
+        list
+    }
+}
@@ -0,0 +1,142 @@
+#[cfg(feature = "stats")]
+use super::{SlotIndex, Tree};
+#[cfg(feature = "stats")]
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+#[cfg(feature = "stats")]
+impl<T, I: SlotIndex, A> Tree<T, I, A> {
+    /// Returns what the tree has done since it was created or
+    /// `reset_stats` was last called. Only with the `stats` feature.
+    #[must_use]
+    pub fn stats(&self) -> Stats {
+        self.counters.get()
+    }
+
+    /// Sets every count back to 0. Only with the `stats` feature.
+    pub fn reset_stats(&mut self) {
+        self.counters = Counters::new();
+    }
+}
+
+/// Counts of what a tree has done since it was created or its stats were
+/// last reset. Only with the `stats` feature.
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Rotations of one node around another.
+    pub single_rotations: u64,
+    /// Pairs of rotations made to balance a node leaning away from its
+    /// taller child.
+    pub double_rotations: u64,
+    /// Comparisons made while searching for a value or a bound.
+    pub comparisons: u64,
+    /// The most levels of nodes the tree has had after an insertion.
+    pub max_depth: u64,
+    /// Values stored in a new slot at the end of the slab.
+    pub allocations: u64,
+    /// Values stored in a vacant slot taken from the free list.
+    pub free_list_hits: u64,
+}
+
+// Kept by every tree, so that counting needs no cfg at the call sites.
+// Without the `stats` feature, it's empty and counts nothing. Counting
+// happens behind shared references, so the counts are atomics.
+#[cfg(feature = "stats")]
+pub(crate) struct Counters {
+    single_rotations: AtomicU64,
+    double_rotations: AtomicU64,
+    comparisons: AtomicU64,
+    max_depth: AtomicU64,
+    allocations: AtomicU64,
+    free_list_hits: AtomicU64,
+}
+
+#[cfg(not(feature = "stats"))]
+#[derive(Clone)]
+pub(crate) struct Counters;
+
+#[cfg(feature = "stats")]
+impl Counters {
+    pub const fn new() -> Self {
+        Self {
+            single_rotations: AtomicU64::new(0),
+            double_rotations: AtomicU64::new(0),
+            comparisons: AtomicU64::new(0),
+            max_depth: AtomicU64::new(0),
+            allocations: AtomicU64::new(0),
+            free_list_hits: AtomicU64::new(0),
+        }
+    }
+
+    pub fn single_rotation(&self) {
+        self.single_rotations.fetch_add(1, Relaxed);
+    }
+
+    pub fn double_rotation(&self) {
+        self.double_rotations.fetch_add(1, Relaxed);
+    }
+
+    pub fn comparison(&self) {
+        self.comparisons.fetch_add(1, Relaxed);
+    }
+
+    // Records that the tree reached HEIGHT, where a single node has a
+    // height of 0.
+    pub fn height(&self, height: i8) {
+        let depth = u64::from(height.unsigned_abs()) + 1;
+        self.max_depth.fetch_max(depth, Relaxed);
+    }
+
+    pub fn allocation(&self) {
+        self.allocations.fetch_add(1, Relaxed);
+    }
+
+    pub fn free_list_hit(&self) {
+        self.free_list_hits.fetch_add(1, Relaxed);
+    }
+
+    pub fn get(&self) -> Stats {
+        Stats {
+            single_rotations: self.single_rotations.load(Relaxed),
+            double_rotations: self.double_rotations.load(Relaxed),
+            comparisons: self.comparisons.load(Relaxed),
+            max_depth: self.max_depth.load(Relaxed),
+            allocations: self.allocations.load(Relaxed),
+            free_list_hits: self.free_list_hits.load(Relaxed),
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+impl Clone for Counters {
+    fn clone(&self) -> Self {
+        let stats = self.get();
+        Self {
+            single_rotations: AtomicU64::new(stats.single_rotations),
+            double_rotations: AtomicU64::new(stats.double_rotations),
+            comparisons: AtomicU64::new(stats.comparisons),
+            max_depth: AtomicU64::new(stats.max_depth),
+            allocations: AtomicU64::new(stats.allocations),
+            free_list_hits: AtomicU64::new(stats.free_list_hits),
+        }
+    }
+}
+
+#[cfg(not(feature = "stats"))]
+impl Counters {
+    pub const fn new() -> Self {
+        Self
+    }
+
+    pub const fn single_rotation(&self) {}
+
+    pub const fn double_rotation(&self) {}
+
+    pub const fn comparison(&self) {}
+
+    pub const fn height(&self, _: i8) {}
+
+    pub const fn allocation(&self) {}
+
+    pub const fn free_list_hit(&self) {}
+}
@@ -0,0 +1,119 @@
+use super::{Augment, DuplicatePolicy, ShrinkPolicy, SlotIndex, Tree};
+use std::mem;
+use std::ops::Deref;
+
+// Undoes one change made in a transaction.
+enum Undo<T> {
+    // A value was inserted at the index.
+    Inserted(usize),
+    // The value at the index replaced this equal one.
+    Replaced(usize, T),
+    // This value was removed from the index, which had the generation.
+    Removed(usize, T, u32),
+}
+
+/// Changes to a tree that are undone when it's dropped, unless they were
+/// committed. Derefs to the tree to read it. See `Tree::transaction`.
+pub struct Transaction<'a, T: Ord, I: SlotIndex = u32, A: Augment<T> = ()> {
+    tree: &'a mut Tree<T, I, A>,
+    journal: Vec<Undo<T>>,
+    // The number of slots before any change, which slots added since
+    // are trimmed back to on rollback.
+    slab_len: usize,
+}
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>> Tree<T, I, A> {
+    /// Calls CHANGES with a transaction on the tree, and keeps its changes
+    /// only if CHANGES returns Ok. If it returns an error or panics, they
+    /// are undone in reverse, so every value that was in the tree is back
+    /// at its index and its handles resolve again. Unless the shrink policy
+    /// is `Never`, slots added since are dropped. The tree's shape may
+    /// differ.
+    pub fn transaction<R, E, F>(&mut self, changes: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut Transaction<'_, T, I, A>) -> Result<R, E>,
+    {
+        let mut transaction = Transaction {
+            slab_len: self.slab.len(),
+            tree: self,
+            journal: Vec::new(),
+        };
+        let result = changes(&mut transaction);
+        if result.is_ok() {
+            transaction.journal.clear();
+        }
+        result
+    }
+}
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>> Transaction<'_, T, I, A> {
+    /// Inserts VALUE like `Tree::insert`.
+    pub fn insert(&mut self, value: T) -> Option<usize> {
+        let (index, value) = match self.tree.try_insert(value) {
+            Ok(index) => {
+                self.journal.push(Undo::Inserted(index));
+                return Some(index);
+            }
+            Err(error) => (error.index, error.value),
+        };
+
+        match self.tree.duplicate_policy {
+            DuplicatePolicy::ReplaceExisting => {
+                let old = mem::replace(&mut self.tree.slab[index].value, value);
+                self.journal.push(Undo::Replaced(index, old));
+                Some(index)
+            }
+            DuplicatePolicy::Reject | DuplicatePolicy::KeepBoth => {
+                let index = self.tree.insert_duplicate(index, value)?;
+                self.journal.push(Undo::Inserted(index));
+                Some(index)
+            }
+        }
+    }
+
+    /// Removes VALUE like `Tree::remove`. Returns whether it was in the
+    /// tree. The removed value is kept until the transaction ends, in case
+    /// it's put back.
+    pub fn remove(&mut self, value: T) -> bool {
+        if self.tree.is_empty() {
+            return false;
+        }
+        let Ok(index) = self.tree.search(&value) else {
+            return false;
+        };
+
+        let generation = self.tree.slab.generation(index);
+        let removed = self.tree.remove_index(index);
+        self.journal.push(Undo::Removed(index, removed, generation));
+        true
+    }
+}
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>> Deref for Transaction<'_, T, I, A> {
+    type Target = Tree<T, I, A>;
+
+    fn deref(&self) -> &Self::Target {
+        self.tree
+    }
+}
+
+impl<T: Ord, I: SlotIndex, A: Augment<T>> Drop for Transaction<'_, T, I, A> {
+    fn drop(&mut self) {
+        // A committed transaction has an empty journal.
+        let rolled_back = !self.journal.is_empty();
+        while let Some(undo) = self.journal.pop() {
+            match undo {
+                Undo::Inserted(index) => {
+                    self.tree.remove_index(index);
+                }
+                Undo::Replaced(index, value) => self.tree.slab[index].value = value,
+                Undo::Removed(index, value, generation) => {
+                    self.tree.restore(index, value, generation);
+                }
+            }
+        }
+        if rolled_back && self.tree.shrink_policy != ShrinkPolicy::Never {
+            self.tree.slab.trim_to(self.slab_len);
+        }
+    }
+}
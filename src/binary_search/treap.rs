@@ -0,0 +1,273 @@
+use super::linked::{Linked, Node};
+use super::{InvariantError, SlotIndex};
+use std::hash::{BuildHasher, RandomState};
+use std::mem;
+use std::ops::RangeBounds;
+
+/// A treap stored in a `Vec`, like `Tree`. Every value gets a random
+/// priority, and parents have higher priorities than their children, so
+/// the tree is balanced in expectation whatever the order of insertion.
+/// Split and join only walk O(log n) nodes down.
+#[derive(Clone)]
+pub struct Treap<T, I = u32> {
+    // Each node's metadata is its priority.
+    links: Linked<T, I, u32>,
+    // The state of the priority generator, or 0 before it's seeded.
+    seed: u64,
+}
+
+impl<T> Default for Treap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, I: SlotIndex> Treap<T, I> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            links: Linked::new(),
+            seed: 0,
+        }
+    }
+
+    /// Creates an empty treap with room for CAPACITY values.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            links: Linked::with_capacity(capacity),
+            seed: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.links.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.links.size == 0
+    }
+
+    /// Returns a reference to the value at INDEX if it exists.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.links.get(index)
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.links.indices().map(|n| &self.links.slab[n].value)
+    }
+
+    // Returns the next priority from a xorshift generator, seeded from
+    // the OS the first time.
+    fn next_priority(&mut self) -> u32 {
+        if self.seed == 0 {
+            self.seed = RandomState::new().hash_one(self.links.slab.len()) | 1;
+        }
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed >> 32) as u32
+    }
+
+    fn priority(&self, index: Option<usize>) -> Option<u32> {
+        index.map(|n| self.links.slab[n].meta)
+    }
+
+    // Joins the subtrees at LEFT and RIGHT, whose values are all smaller
+    // on the left. Returns the root, whose parent is left unset.
+    fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+        let (Some(l), Some(r)) = (left, right) else {
+            return left.or(right);
+        };
+
+        if self.priority(left) > self.priority(right) {
+            let merged = self.merge(self.links.slab[l].right(), right);
+            self.links.link_right(l, merged);
+            Some(l)
+        } else {
+            let merged = self.merge(left, self.links.slab[r].left());
+            self.links.link_left(r, merged);
+            Some(r)
+        }
+    }
+
+    // Makes the subtree at ROOT the whole tree.
+    fn set_root(&mut self, root: Option<usize>) {
+        if let Some(n) = root {
+            self.links.slab[n].set_parent(None);
+            self.links.root = n;
+        }
+    }
+}
+
+impl<T: Ord, I: SlotIndex> Treap<T, I> {
+    /// Returns the index of VALUE if it is found.
+    pub fn contains(&self, value: T) -> Option<usize> {
+        self.links.search(&value).ok()
+    }
+
+    /// Returns an iterator over the values within BOUNDS in ascending
+    /// order. Finding the first takes O(log n).
+    pub fn range<R: RangeBounds<T>>(&self, bounds: R) -> impl Iterator<Item = &T> {
+        self.links.range(bounds)
+    }
+
+    /// Inserts VALUE, returning its index, or None if it's already in
+    /// the treap.
+    pub fn insert(&mut self, value: T) -> Option<usize> {
+        let (parent, ordering) = self.links.search(&value).err()?;
+        let priority = self.next_priority();
+        let index = self
+            .links
+            .attach(parent, ordering, Node::new(value, priority));
+
+        // Rotate the new node up until its parent outranks it.
+        while let Some(parent) = self.links.slab[index].parent() {
+            if self.links.slab[parent].meta >= priority {
+                break;
+            }
+            if self.links.slab[parent].left() == Some(index) {
+                self.links.rotate_right(parent);
+            } else {
+                self.links.rotate_left(parent);
+            }
+        }
+        Some(index)
+    }
+
+    /// Removes VALUE from the treap and returns it, if it was there.
+    pub fn remove(&mut self, value: T) -> Option<T> {
+        let index = self.links.search(&value).ok()?;
+
+        // Rotate the node down under its higher priority child until it
+        // has at most one child, so that unlinking doesn't move another.
+        loop {
+            let node_data = &self.links.slab[index];
+            let (left, right) = (node_data.left(), node_data.right());
+            if left.is_none() || right.is_none() {
+                break;
+            }
+            if self.priority(left) > self.priority(right) {
+                self.links.rotate_right(index);
+            } else {
+                self.links.rotate_left(index);
+            }
+        }
+
+        self.links.unlink(index);
+        Some(self.links.slab.remove(index).value)
+    }
+
+    /// Moves every value greater than or equal to VALUE into a new treap
+    /// and returns it. Splitting walks O(log n) nodes, but the nodes on
+    /// the smaller side are copied over, so their indices change.
+    pub fn split_off(&mut self, value: &T) -> Self {
+        let mut other = Self::new();
+        if self.is_empty() {
+            return other;
+        }
+
+        let root = self.links.root;
+        let (left, right) = self.split(Some(root), value);
+        for n in [left, right].into_iter().flatten() {
+            self.links.slab[n].set_parent(None);
+        }
+
+        // Count both sides in step, stopping at the end of the smaller.
+        let mut len = 0;
+        let smaller_is_left = {
+            let mut left = self.links.subtree_indices(left);
+            let mut right = self.links.subtree_indices(right);
+            loop {
+                match (left.next(), right.next()) {
+                    (None, _) => break true,
+                    (_, None) => break false,
+                    _ => len += 1,
+                }
+            }
+        };
+
+        let (smaller, larger) = if smaller_is_left {
+            (left, right)
+        } else {
+            (right, left)
+        };
+        if let Some(n) = smaller {
+            other.links = self.links.take_subtree(n, len);
+        }
+        self.set_root(larger);
+        if smaller_is_left {
+            mem::swap(&mut self.links, &mut other.links);
+        }
+        other
+    }
+
+    // Splits the subtree at INDEX into values less than VALUE and the
+    // rest. Returns both roots, whose parents are left unset.
+    fn split(&mut self, index: Option<usize>, value: &T) -> (Option<usize>, Option<usize>) {
+        let Some(n) = index else {
+            return (None, None);
+        };
+
+        if self.links.slab[n].value < *value {
+            let (left, right) = self.split(self.links.slab[n].right(), value);
+            self.links.link_right(n, left);
+            (Some(n), right)
+        } else {
+            let (left, right) = self.split(self.links.slab[n].left(), value);
+            self.links.link_left(n, right);
+            (left, Some(n))
+        }
+    }
+
+    /// Moves every value of OTHER into the treap when they are all larger
+    /// than the values already in it, returning OTHER unchanged otherwise.
+    /// Joining walks O(log n) nodes, but the nodes of the smaller treap are
+    /// copied over, so their indices change.
+    pub fn concat_disjoint(&mut self, mut other: Self) -> Result<(), Self> {
+        if other.is_empty() {
+            return Ok(());
+        }
+        if self.is_empty() {
+            mem::swap(&mut self.links, &mut other.links);
+            return Ok(());
+        }
+
+        let self_max = &self.links.slab[self.links.max_index(self.links.root)].value;
+        let other_min = &other.links.slab[other.links.min_index(other.links.root)].value;
+        if self_max >= other_min {
+            return Err(other);
+        }
+
+        let (left, right) = if other.links.slab.len() > self.links.slab.len() {
+            mem::swap(&mut self.links, &mut other.links);
+            let left = self.links.absorb(other.links);
+            (left, self.links.root)
+        } else {
+            let right = self.links.absorb(other.links);
+            (self.links.root, right)
+        };
+
+        let root = self.merge(Some(left), Some(right));
+        self.set_root(root);
+        Ok(())
+    }
+
+    /// Checks that the treap is ordered, that no node has a lower priority
+    /// than its children, and that every node is reachable from the root
+    /// and linked to its parent. Priority errors are reported as `Balance`.
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        // Each subtree returns its root's priority.
+        self.links.validate(None, |index, node_data, left, right| {
+            let priority = Some(node_data.meta);
+            if left > priority || right > priority {
+                return Err(InvariantError::Balance(index));
+            }
+            Ok(priority)
+        })
+    }
+}
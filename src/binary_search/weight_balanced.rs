@@ -0,0 +1,231 @@
+use super::linked::{Linked, Node};
+use super::{InvariantError, Rank, SlotIndex};
+use std::cmp::Ordering;
+use std::ops::{Index, RangeBounds};
+
+// A subtree is too heavy when its weight, its length plus one, is more
+// than DELTA times its sibling's. It's rebalanced with a double rotation
+// when its inner child weighs at least GAMMA times its outer child, the
+// parameters from Hirai and Yamamoto's proof.
+const DELTA: usize = 3;
+const GAMMA: usize = 2;
+
+/// A weight-balanced tree stored in a `Vec`, like `Tree`. Every node keeps
+/// the length of its subtree to balance by, which also finds values by
+/// rank in O(log n). It's less tightly balanced than `Tree`.
+#[derive(Clone)]
+pub struct WbTree<T, I = u32> {
+    // Each node's metadata is the length of its subtree.
+    links: Linked<T, I, I>,
+}
+
+impl<T> Default for WbTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, I: SlotIndex> WbTree<T, I> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            links: Linked::new(),
+        }
+    }
+
+    /// Creates an empty tree with room for CAPACITY values.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            links: Linked::with_capacity(capacity),
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.links.size
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.links.size == 0
+    }
+
+    /// Returns a reference to the value at INDEX if it exists.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.links.get(index)
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.links.indices().map(|n| &self.links.slab[n].value)
+    }
+
+    /// Returns the Kth smallest value, counting from 0, or None if the
+    /// tree has K or fewer values.
+    #[must_use]
+    pub fn select(&self, mut k: usize) -> Option<&T> {
+        if k >= self.len() {
+            return None;
+        }
+
+        let mut index = self.links.root;
+        loop {
+            let node_data = &self.links.slab[index];
+            let left_len = self.subtree_len(node_data.left());
+            index = match k.cmp(&left_len) {
+                Ordering::Less => node_data.left()?,
+                Ordering::Equal => return Some(&node_data.value),
+                Ordering::Greater => {
+                    k -= left_len + 1;
+                    node_data.right()?
+                }
+            };
+        }
+    }
+
+    fn subtree_len(&self, index: Option<usize>) -> usize {
+        index.map_or(0, |n| self.links.slab[n].meta.to_usize())
+    }
+
+    fn update_len(&mut self, index: usize) {
+        let node_data = &self.links.slab[index];
+        let len = self.subtree_len(node_data.left()) + 1 + self.subtree_len(node_data.right());
+        self.links.slab[index].meta = I::from_usize(len);
+    }
+
+    // Walks up from INDEX, updating lengths and rebalancing every node on
+    // the way to the root.
+    fn update_and_balance(&mut self, mut index: Option<usize>) {
+        while let Some(n) = index {
+            self.update_len(n);
+            let root = self.balance_node(n);
+            index = self.links.slab[root].parent();
+        }
+    }
+
+    // Rotates the subtree at INDEX if one side is too heavy, assuming
+    // both sides are balanced. Returns the subtree's new root.
+    fn balance_node(&mut self, index: usize) -> usize {
+        let node_data = &self.links.slab[index];
+        let (left, right) = (node_data.left(), node_data.right());
+        let left_weight = self.subtree_len(left) + 1;
+        let right_weight = self.subtree_len(right) + 1;
+
+        if right_weight > DELTA * left_weight {
+            let Some(right) = right else { unreachable!() };
+            let (inner, outer) = (
+                self.links.slab[right].left(),
+                self.links.slab[right].right(),
+            );
+            if self.subtree_len(inner) + 1 >= GAMMA * (self.subtree_len(outer) + 1) {
+                let root = self.links.rotate_right(right);
+                self.update_len(right);
+                self.update_len(root);
+            }
+            let root = self.links.rotate_left(index);
+            self.update_len(index);
+            self.update_len(root);
+            root
+        } else if left_weight > DELTA * right_weight {
+            let Some(left) = left else { unreachable!() };
+            let (inner, outer) = (self.links.slab[left].right(), self.links.slab[left].left());
+            if self.subtree_len(inner) + 1 >= GAMMA * (self.subtree_len(outer) + 1) {
+                let root = self.links.rotate_left(left);
+                self.update_len(left);
+                self.update_len(root);
+            }
+            let root = self.links.rotate_right(index);
+            self.update_len(index);
+            self.update_len(root);
+            root
+        } else {
+            index
+        }
+    }
+}
+
+impl<T: Ord, I: SlotIndex> WbTree<T, I> {
+    /// Returns the index of VALUE if it is found.
+    pub fn contains(&self, value: T) -> Option<usize> {
+        self.links.search(&value).ok()
+    }
+
+    /// Returns the number of values smaller than VALUE, which is its rank
+    /// if it's in the tree.
+    #[must_use]
+    pub fn rank(&self, value: &T) -> usize {
+        let mut rank = 0;
+        let mut current = (!self.is_empty()).then_some(self.links.root);
+        while let Some(n) = current {
+            let node_data = &self.links.slab[n];
+            current = match value.cmp(&node_data.value) {
+                Ordering::Less => node_data.left(),
+                Ordering::Equal => return rank + self.subtree_len(node_data.left()),
+                Ordering::Greater => {
+                    rank += self.subtree_len(node_data.left()) + 1;
+                    node_data.right()
+                }
+            };
+        }
+        rank
+    }
+
+    /// Returns an iterator over the values within BOUNDS in ascending
+    /// order. Finding the first takes O(log n).
+    pub fn range<R: RangeBounds<T>>(&self, bounds: R) -> impl Iterator<Item = &T> {
+        self.links.range(bounds)
+    }
+
+    /// Inserts VALUE, returning its index, or None if it's already in
+    /// the tree.
+    pub fn insert(&mut self, value: T) -> Option<usize> {
+        let (parent, ordering) = self.links.search(&value).err()?;
+        let node = Node::new(value, I::from_usize(1));
+        let index = self.links.attach(parent, ordering, node);
+        self.update_and_balance(parent);
+        Some(index)
+    }
+
+    /// Removes VALUE from the tree and returns it, if it was there.
+    pub fn remove(&mut self, value: T) -> Option<T> {
+        let index = self.links.search(&value).ok()?;
+        let unlinked = self.links.unlink(index);
+        self.update_and_balance(unlinked.child_parent);
+        Some(self.links.slab.remove(index).value)
+    }
+
+    /// Checks that the tree is ordered and balanced, and that every node
+    /// is reachable from the root and linked to its parent. Wrong subtree
+    /// lengths are reported as `Augment`.
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        // Each subtree returns its length.
+        self.links.validate(0, |index, node_data, left, right| {
+            let (left_weight, right_weight) = (left + 1, right + 1);
+            if left_weight > DELTA * right_weight || right_weight > DELTA * left_weight {
+                return Err(InvariantError::Balance(index));
+            }
+            if node_data.meta.to_usize() != left + 1 + right {
+                return Err(InvariantError::Augment(index));
+            }
+            Ok(left + 1 + right)
+        })
+    }
+}
+
+impl<T, I: SlotIndex> Index<Rank> for WbTree<T, I> {
+    type Output = T;
+
+    /// Returns the value at RANK, like `select`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tree has RANK or fewer values.
+    fn index(&self, rank: Rank) -> &T {
+        match self.select(rank.0) {
+            Some(value) => value,
+            None => panic!("rank {} is out of range for length {}", rank.0, self.len()),
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! A C interface to `Tree`, as sets of `i64`s or of pointers ordered by a
+//! comparator. Sets and iterators are opaque handles that must be freed
+//! with their `_free` function. Every pointer passed in must be one
+//! returned by this module and not yet freed, unless noted otherwise.
+
+use crate::binary_search::Tree;
+use std::cmp::Ordering;
+use std::ffi::{c_int, c_void};
+use std::ops::Bound;
+
+/// A set of `i64`s.
+pub struct AvlI64 {
+    tree: Tree<i64>,
+}
+
+/// An iterator over an `AvlI64` in ascending order. It remembers the last
+/// value it returned, so the set may change between calls.
+pub struct AvlI64Iter {
+    set: *const AvlI64,
+    last: Option<i64>,
+}
+
+/// Returns a negative number, 0 or a positive number when the first value
+/// is less than, equal to or greater than the second, like for `qsort`.
+pub type AvlCompare = extern "C" fn(*const c_void, *const c_void) -> c_int;
+
+// A pointer ordered by the comparator of its set.
+struct Entry {
+    value: *const c_void,
+    compare: AvlCompare,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.compare)(self.value, other.value).cmp(&0)
+    }
+}
+
+/// A set of pointers, ordered by the values they point to. The pointers
+/// are stored as they are, so their values must outlive the set.
+pub struct AvlPtr {
+    tree: Tree<Entry>,
+    compare: AvlCompare,
+}
+
+/// An iterator over an `AvlPtr` in ascending order. It remembers the last
+/// pointer it returned, so the set may change between calls.
+pub struct AvlPtrIter {
+    set: *const AvlPtr,
+    last: Option<*const c_void>,
+}
+
+/// Returns a new, empty set of `i64`s.
+#[no_mangle]
+pub extern "C" fn avl_i64_new() -> *mut AvlI64 {
+    Box::into_raw(Box::new(AvlI64 { tree: Tree::new() }))
+}
+
+/// Frees SET. Null is ignored.
+///
+/// # Safety
+///
+/// SET must be null or a live set, with no iterators left over it.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_free(set: *mut AvlI64) {
+    if !set.is_null() {
+        // SAFETY: the caller guarantees SET came from `avl_i64_new`.
+        drop(unsafe { Box::from_raw(set) });
+    }
+}
+
+/// Inserts VALUE into SET. Returns whether it wasn't already there.
+///
+/// # Safety
+///
+/// SET must be a live set.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_insert(set: *mut AvlI64, value: i64) -> bool {
+    // SAFETY: the caller guarantees SET is live.
+    unsafe { &mut *set }.tree.insert(value).is_some()
+}
+
+/// Returns whether VALUE is in SET.
+///
+/// # Safety
+///
+/// SET must be a live set.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_contains(set: *const AvlI64, value: i64) -> bool {
+    // SAFETY: the caller guarantees SET is live.
+    unsafe { &*set }.tree.contains(value).is_some()
+}
+
+/// Removes VALUE from SET. Returns whether it was there.
+///
+/// # Safety
+///
+/// SET must be a live set.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_remove(set: *mut AvlI64, value: i64) -> bool {
+    // SAFETY: the caller guarantees SET is live.
+    unsafe { &mut *set }.tree.remove(value).is_some()
+}
+
+/// Returns the number of values in SET.
+///
+/// # Safety
+///
+/// SET must be a live set.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_len(set: *const AvlI64) -> usize {
+    // SAFETY: the caller guarantees SET is live.
+    unsafe { &*set }.tree.len()
+}
+
+/// Returns an iterator over SET, which must outlive it.
+///
+/// # Safety
+///
+/// SET must be a live set.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_iter(set: *const AvlI64) -> *mut AvlI64Iter {
+    Box::into_raw(Box::new(AvlI64Iter { set, last: None }))
+}
+
+/// Writes the next value of ITER to OUT and returns true, or returns false
+/// once there are no more. Takes O(log n).
+///
+/// # Safety
+///
+/// ITER must be a live iterator whose set is live, and OUT must be valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_iter_next(iter: *mut AvlI64Iter, out: *mut i64) -> bool {
+    // SAFETY: the caller guarantees ITER and its set are live.
+    let iter = unsafe { &mut *iter };
+    let tree = &unsafe { &*iter.set }.tree;
+
+    let lower = match &iter.last {
+        Some(last) => Bound::Excluded(last),
+        None => Bound::Unbounded,
+    };
+    let Some(&value) = tree.lower_bound(lower).current() else {
+        return false;
+    };
+    iter.last = Some(value);
+    // SAFETY: the caller guarantees OUT is valid for writes.
+    unsafe { out.write(value) };
+    true
+}
+
+/// Frees ITER. Null is ignored.
+///
+/// # Safety
+///
+/// ITER must be null or a live iterator.
+#[no_mangle]
+pub unsafe extern "C" fn avl_i64_iter_free(iter: *mut AvlI64Iter) {
+    if !iter.is_null() {
+        // SAFETY: the caller guarantees ITER came from `avl_i64_iter`.
+        drop(unsafe { Box::from_raw(iter) });
+    }
+}
+
+/// Returns a new, empty set of pointers ordered by COMPARE.
+#[no_mangle]
+pub extern "C" fn avl_ptr_new(compare: AvlCompare) -> *mut AvlPtr {
+    Box::into_raw(Box::new(AvlPtr {
+        tree: Tree::new(),
+        compare,
+    }))
+}
+
+/// Frees SET, but not the values its pointers point to. Null is ignored.
+///
+/// # Safety
+///
+/// SET must be null or a live set, with no iterators left over it.
+#[no_mangle]
+pub unsafe extern "C" fn avl_ptr_free(set: *mut AvlPtr) {
+    if !set.is_null() {
+        // SAFETY: the caller guarantees SET came from `avl_ptr_new`.
+        drop(unsafe { Box::from_raw(set) });
+    }
+}
+
+/// Inserts VALUE into SET. Returns whether no equal value was already
+/// there. VALUE must not be null, which marks the end of iteration.
+///
+/// # Safety
+///
+/// SET must be a live set, and VALUE must be valid for its comparator.
+#[no_mangle]
+pub unsafe extern "C" fn avl_ptr_insert(set: *mut AvlPtr, value: *const c_void) -> bool {
+    // SAFETY: the caller guarantees SET is live.
+    let set = unsafe { &mut *set };
+    let compare = set.compare;
+    set.tree.insert(Entry { value, compare }).is_some()
+}
+
+/// Returns whether a value equal to VALUE is in SET.
+///
+/// # Safety
+///
+/// SET must be a live set, and VALUE must be valid for its comparator.
+#[no_mangle]
+pub unsafe extern "C" fn avl_ptr_contains(set: *const AvlPtr, value: *const c_void) -> bool {
+    // SAFETY: the caller guarantees SET is live.
+    let set = unsafe { &*set };
+    let compare = set.compare;
+    set.tree.contains(Entry { value, compare }).is_some()
+}
+
+/// Removes the value equal to VALUE from SET. Returns the pointer that
+/// was stored, or null if there was none.
+///
+/// # Safety
+///
+/// SET must be a live set, and VALUE must be valid for its comparator.
+#[no_mangle]
+pub unsafe extern "C" fn avl_ptr_remove(set: *mut AvlPtr, value: *const c_void) -> *const c_void {
+    // SAFETY: the caller guarantees SET is live.
+    let set = unsafe { &mut *set };
+    let compare = set.compare;
+    match set.tree.remove(Entry { value, compare }) {
+        Some(entry) => entry.value,
+        None => std::ptr::null(),
+    }
+}
+
+/// Returns the number of values in SET.
+///
+/// # Safety
+///
+/// SET must be a live set.
+#[no_mangle]
+pub unsafe extern "C" fn avl_ptr_len(set: *const AvlPtr) -> usize {
+    // SAFETY: the caller guarantees SET is live.
+    unsafe { &*set }.tree.len()
+}
+
+/// Returns an iterator over SET, which must outlive it.
+///
+/// # Safety
+///
+/// SET must be a live set.
+#[no_mangle]
+pub unsafe extern "C" fn avl_ptr_iter(set: *const AvlPtr) -> *mut AvlPtrIter {
+    Box::into_raw(Box::new(AvlPtrIter { set, last: None }))
+}
+
+/// Returns the next pointer of ITER, or null once there are no more.
+/// Takes O(log n). The last pointer returned must stay valid for the
+/// comparator until the next call, even if it's removed from the set.
+///
+/// # Safety
+///
+/// ITER must be a live iterator whose set is live.
+#[no_mangle]
+pub unsafe extern "C" fn avl_ptr_iter_next(iter: *mut AvlPtrIter) -> *const c_void {
+    // SAFETY: the caller guarantees ITER and its set are live.
+    let iter = unsafe { &mut *iter };
+    let set = unsafe { &*iter.set };
+
+    let last = iter.last.map(|value| Entry {
+        value,
+        compare: set.compare,
+    });
+    let lower = match &last {
+        Some(last) => Bound::Excluded(last),
+        None => Bound::Unbounded,
+    };
+    match set.tree.lower_bound(lower).current() {
+        Some(entry) => {
+            iter.last = Some(entry.value);
+            entry.value
+        }
+        None => std::ptr::null(),
+    }
+}
+
+/// Frees ITER. Null is ignored.
+///
+/// # Safety
+///
+/// ITER must be null or a live iterator.
+#[no_mangle]
+pub unsafe extern "C" fn avl_ptr_iter_free(iter: *mut AvlPtrIter) {
+    if !iter.is_null() {
+        // SAFETY: the caller guarantees ITER came from `avl_ptr_iter`.
+        drop(unsafe { Box::from_raw(iter) });
+    }
+}
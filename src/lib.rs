@@ -1,4 +1,15 @@
 pub mod binary_search;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+/// Builds a `binary_search::Tree` from a list of values, sorted and
+/// deduplicated like `Tree::from`, with the first of equal values kept.
+#[macro_export]
+macro_rules! avl {
+    ($($value:expr),* $(,)?) => {
+        $crate::binary_search::Tree::from([$($value),*])
+    };
+}
 
 #[cfg(test)]
 mod data_structures {
@@ -23,4 +34,2080 @@ mod data_structures {
         // Get a reference to the value.
         assert_eq!(tree.get(value_index).unwrap(), &732);
     }
+
+    #[test]
+    fn validate_against() {
+        let tree = binary_search::Tree::from([1, 2, 3, 4]);
+        assert_eq!(tree.validate_against(1..5), Ok(()));
+
+        let err = tree.validate_against([1, 2, 5, 4]).unwrap_err();
+        assert_eq!(
+            err,
+            binary_search::Divergence::Value {
+                index: 2,
+                expected: 5,
+                found: &3
+            }
+        );
+
+        let err = tree.validate_against(1..4).unwrap_err();
+        assert_eq!(
+            err,
+            binary_search::Divergence::Extra {
+                index: 3,
+                found: &4
+            }
+        );
+
+        let err = tree.validate_against(1..7).unwrap_err();
+        assert_eq!(
+            err,
+            binary_search::Divergence::Missing {
+                index: 4,
+                expected: 5
+            }
+        );
+        assert_eq!(err.index(), 4);
+    }
+
+    #[test]
+    fn conversions() {
+        let mut tree = binary_search::Tree::from(vec![5, 3, 9, 3, 1]);
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.insert(3), None);
+        assert!(tree.insert(4).is_some());
+
+        let tree = binary_search::Tree::from([2, 1, 0]);
+        assert_eq!(tree.get(tree.contains(2).unwrap()), Some(&2));
+
+        let sorted = binary_search::SortedVec(vec![1, 2, 2]);
+        let err = binary_search::Tree::try_from(sorted).err().unwrap();
+        assert_eq!(err.index(), 2);
+
+        let sorted = binary_search::SortedVec((0..100).collect());
+        let mut tree = binary_search::Tree::try_from(sorted).unwrap();
+        for n in 0..100 {
+            assert_eq!(tree.remove(n), Some(n));
+        }
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn iterators() {
+        let mut tree = binary_search::Tree::default();
+        for n in [4, 2, 6, 1, 3, 5, 7] {
+            tree.insert(n);
+        }
+
+        for n in &mut tree {
+            *n *= 10;
+        }
+
+        let mut values = Vec::new();
+        for n in &tree {
+            values.push(*n);
+        }
+        assert_eq!(values, [10, 20, 30, 40, 50, 60, 70]);
+
+        let mut output = Vec::new();
+        tree.write_sorted(&mut output, b", ", |n, buffer| {
+            buffer.extend_from_slice(n.to_string().as_bytes());
+        })
+        .unwrap();
+        assert_eq!(output, b"10, 20, 30, 40, 50, 60, 70");
+    }
+
+    #[test]
+    fn concat() {
+        for (left, right) in [(0, 1), (1, 40), (40, 1), (300, 7), (7, 300), (64, 64)] {
+            let mut tree: binary_search::Tree<_, u16> =
+                binary_search::Tree::from_sorted_unchecked((0..left).collect());
+            let other = binary_search::Tree::from_sorted_unchecked((left..left + right).collect());
+            let handle = match left {
+                0 => None,
+                _ => tree.handle(left - 1),
+            };
+            assert!(tree.concat_disjoint(other).is_ok());
+            assert_eq!(tree.len(), left + right);
+            if let Some(handle) = handle {
+                let moved = right > left;
+                assert_eq!(tree.get_handle(handle), (!moved).then_some(&(left - 1)));
+            }
+
+            for n in left / 2..left + right {
+                assert_eq!(tree.remove(n), Some(n));
+            }
+            assert!(tree.iter().copied().eq(0..left / 2));
+        }
+
+        let mut tree = binary_search::Tree::from([1, 5]);
+        assert!(tree
+            .concat_disjoint(binary_search::Tree::from([3]))
+            .is_err());
+    }
+
+    #[test]
+    fn seeded_search() {
+        let tree = binary_search::Tree::from((0..500).collect::<Vec<u32>>());
+
+        let mut hint = tree.contains(0).unwrap();
+        for n in 0..500 {
+            hint = tree.binary_search_seeded(hint, &n).unwrap();
+            assert_eq!(tree.get(hint), Some(&n));
+        }
+        assert_eq!(tree.binary_search_seeded(hint, &500), None);
+        assert_eq!(tree.binary_search_seeded(usize::MAX, &7), tree.contains(7));
+    }
+
+    #[test]
+    fn dirty_tracking() {
+        let mut tree = binary_search::Tree::from([1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(tree.dirty_slots().count(), 7);
+
+        tree.clear_dirty();
+        assert_eq!(tree.dirty_slots().count(), 0);
+
+        let index = tree.contains(7).unwrap();
+        tree.remove(7);
+        assert!(tree
+            .dirty_slots()
+            .any(|(n, value)| n == index && value.is_none()));
+        assert!(tree.dirty_slots().count() < 7);
+    }
+
+    #[test]
+    fn shrink_policy() {
+        let mut tree = binary_search::Tree::from([1, 2, 3]);
+        tree.set_shrink_policy(binary_search::ShrinkPolicy::Never);
+
+        let index = tree.contains(3).unwrap();
+        tree.remove(3);
+        assert_ne!(tree.insert(4), Some(index));
+        assert_eq!(tree.get(index), None);
+
+        tree.set_shrink_policy(binary_search::ShrinkPolicy::OnDemand);
+        assert_eq!(tree.insert(3), Some(index));
+
+        let mut tree: binary_search::Tree<u32> = binary_search::Tree::with_capacity(100);
+        assert!(tree.capacity() >= 100);
+        tree.insert(1);
+        tree.shrink_to_fit();
+        assert!(tree.capacity() < 100);
+    }
+
+    #[test]
+    fn slice_set_operations() {
+        let mut tree = binary_search::Tree::from([1, 3, 5, 7]);
+        tree.union_with_slice(&[0, 3, 4, 8]);
+        assert!(tree.iter().eq(&[0, 1, 3, 4, 5, 7, 8]));
+
+        let index = tree.contains(5).unwrap();
+        tree.intersect_with_slice(&[2, 3, 5, 8, 9]);
+        assert!(tree.iter().eq(&[3, 5, 8]));
+        assert_eq!(tree.get(index), Some(&5));
+    }
+
+    #[test]
+    fn compact() {
+        let mut tree = binary_search::Tree::from([1, 2, 3, 4, 5, 6]);
+        tree.set_shrink_policy(binary_search::ShrinkPolicy::Never);
+        let index = tree.contains(5).unwrap();
+        let handle = tree.handle(index).unwrap();
+        tree.remove(2);
+        tree.remove(6);
+
+        let remap = tree.compact();
+        assert_eq!(remap.len(), 6);
+        assert_eq!(remap.iter().flatten().count(), 4);
+        assert_eq!(tree.get(remap[index].unwrap()), Some(&5));
+        assert_eq!(tree.insert(6), Some(4));
+        assert!(tree.iter().eq(&[1, 3, 4, 5, 6]));
+
+        // The handle's slot was dropped, then pushed again.
+        assert_eq!(index, 4);
+        assert_eq!(tree.get_handle(handle), None);
+    }
+
+    #[test]
+    fn raw_nodes() {
+        let tree: binary_search::Tree<u32, u16> =
+            binary_search::Tree::from_sorted_unchecked(vec![1, 2, 3, 4, 5]);
+        let nodes = tree.to_raw();
+        assert_eq!(nodes.len(), 5);
+        assert_eq!(nodes[0].value, 3);
+        assert_eq!(nodes[0].left, 1);
+
+        for value in 1..=5 {
+            let position = binary_search::RawNode::search(&nodes, &value).unwrap();
+            assert_eq!(nodes[position].value, value);
+        }
+        assert_eq!(binary_search::RawNode::search(&nodes, &6), None);
+        assert_eq!(binary_search::RawNode::<u32, u16>::search(&[], &1), None);
+    }
+
+    #[test]
+    fn index_width() {
+        let tree: binary_search::Tree<u32, u64> =
+            binary_search::Tree::from_sorted_unchecked((0..300).collect());
+        let index = tree.contains(200).unwrap();
+        let tree = tree.narrow::<u8>().err().unwrap();
+
+        let mut tree = tree.narrow::<u16>().ok().unwrap();
+        assert_eq!(tree.get(index), Some(&200));
+        tree.remove(200);
+
+        let tree = tree.widen::<u32>();
+        assert_eq!(tree.len(), 299);
+        assert!(tree.iter().copied().eq((0..300).filter(|&n| n != 200)));
+    }
+
+    #[test]
+    fn insert_hint() {
+        let mut tree = binary_search::Tree::default();
+        let mut hint = 0;
+        for value in 0..100 {
+            hint = tree.insert_hint(hint, value).unwrap();
+        }
+        assert!(tree.iter().copied().eq(0..100));
+
+        assert_eq!(tree.insert_hint(hint, 50), None);
+        assert!(tree.insert_hint(hint, -1).is_some());
+        assert_eq!(tree.insert_hint(1000, 100), Some(101));
+        assert_eq!(tree.len(), 102);
+
+        // Once the largest value is removed and its slot reused for a
+        // smaller one, values right of that slot aren't the largest.
+        assert_eq!(tree.remove(100), Some(100));
+        assert_eq!(tree.insert(-5), Some(101));
+        let index = tree.insert(-3).unwrap();
+        assert!(tree.insert_hint(index, 200).is_some());
+        assert!(tree.insert_hint(101, 150).is_some());
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.iter().last(), Some(&200));
+    }
+
+    #[test]
+    fn from_validated() {
+        let values = vec![4, -1, 2, 4, -3, 1];
+        let (tree, errors): (binary_search::Tree<i32>, _) =
+            binary_search::Tree::from_validated(values, |&n| match n >= 0 {
+                true => Ok(()),
+                false => Err(n),
+            });
+
+        assert!(tree.iter().eq(&[1, 2, 4]));
+        assert_eq!(errors, vec![(1, -1), (4, -3)]);
+    }
+
+    #[test]
+    fn validate() {
+        let mut tree = binary_search::Tree::from([5, 3, 8, 1, 4, 7, 9]);
+        assert_eq!(tree.validate(), Ok(()));
+
+        tree.set_shrink_policy(binary_search::ShrinkPolicy::Never);
+        tree.remove(9);
+        tree.remove(1);
+        tree.insert(2);
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(binary_search::Tree::<u8>::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn handles() {
+        let mut tree = binary_search::Tree::from([1, 2, 3]);
+        let index = tree.contains(3).unwrap();
+        let handle = tree.handle(index).unwrap();
+        assert_eq!(tree.get_handle(handle), Some(&3));
+
+        tree.remove(3);
+        assert_eq!(tree.insert(4), Some(index));
+        assert_eq!(tree.get_handle(handle), None);
+        assert_eq!(tree.get_handle(tree.handle(index).unwrap()), Some(&4));
+        assert_eq!(tree.handle(10), None);
+
+        tree.remove(4);
+        assert_eq!(tree.get_handle(handle), None);
+    }
+
+    #[test]
+    fn prune_depth() {
+        let mut tree: binary_search::Tree<u32> =
+            binary_search::Tree::from_sorted_unchecked((0..15).collect());
+        let index = tree.contains(7).unwrap();
+
+        let pruned = tree.prune_depth(1);
+        assert_eq!(pruned, vec![0, 1, 2, 4, 5, 6, 8, 9, 10, 12, 13, 14]);
+        assert!(tree.iter().eq(&[3, 7, 11]));
+        assert_eq!(tree.get(index), Some(&7));
+        assert_eq!(tree.validate(), Ok(()));
+        assert!(tree.prune_depth(5).is_empty());
+    }
+
+    #[test]
+    fn prefixed() {
+        use binary_search::Prefixed;
+
+        let words = ["composite", "compose", "a", "", "compositeness", "b"];
+        let tree = binary_search::Tree::from(words.map(|n| Prefixed::new(n.to_string())));
+        let mut sorted = words.to_vec();
+        sorted.sort_unstable();
+
+        assert!(tree.iter().map(Prefixed::value).eq(&sorted));
+        assert!(tree
+            .contains(Prefixed::new("compose".to_string()))
+            .is_some());
+        assert!(tree
+            .contains(Prefixed::new("composer".to_string()))
+            .is_none());
+    }
+
+    #[test]
+    fn try_insert() {
+        let mut tree = binary_search::Tree::from([(1, 'a'), (2, 'b')]);
+        let index = tree.try_insert((3, 'c')).unwrap();
+
+        let error = tree.try_insert((3, 'c')).unwrap_err();
+        assert_eq!(error.index(), index);
+        assert_eq!(error.existing(), &(3, 'c'));
+        assert_eq!(error.into_value(), (3, 'c'));
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    fn duplicate_policy() {
+        use binary_search::DuplicatePolicy;
+
+        let mut tree = binary_search::Tree::from([(1, 'a'), (2, 'b')]);
+        let index = tree.contains((2, 'b')).unwrap();
+        assert_eq!(tree.insert((2, 'b')), None);
+
+        tree.set_duplicate_policy(DuplicatePolicy::ReplaceExisting);
+        assert_eq!(tree.insert((2, 'b')), Some(index));
+        assert_eq!(tree.len(), 2);
+
+        let mut tree = binary_search::Tree::from([1, 2, 3]);
+        tree.set_duplicate_policy(DuplicatePolicy::KeepBoth);
+        for value in [2, 2, 1, 3, 3] {
+            assert!(tree.insert(value).is_some());
+        }
+        assert!(tree.iter().eq(&[1, 1, 2, 2, 2, 3, 3, 3]));
+        assert_eq!(tree.validate(), Ok(()));
+
+        assert_eq!(tree.remove(2), Some(2));
+        assert_eq!(tree.len(), 7);
+    }
+
+    #[test]
+    fn get() {
+        let mut tree = binary_search::Tree::default();
+        assert_eq!(tree.get(0), None);
+        assert!(!tree.is_occupied(0));
+
+        let index = tree.insert(1).unwrap();
+        assert!(tree.is_occupied(index));
+        assert_eq!(tree.get(index + 1), None);
+        assert_eq!(unsafe { tree.get_unchecked(index) }, &1);
+    }
+
+    // Runs random operations on a tree and a BTreeSet side by side, and
+    // checks that they agree and that the tree stays valid after each one.
+    #[test]
+    fn differential() {
+        use std::collections::{BTreeMap, BTreeSet};
+
+        // Xorshift, so that failures can be reproduced from the seed.
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = |bound: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % bound
+        };
+
+        for _ in 0..200 {
+            let mut tree = binary_search::Tree::default();
+            let mut set = BTreeSet::new();
+            let mut indices = BTreeMap::new();
+            let range = 1 + random(500);
+
+            for _ in 0..random(400) {
+                let value = random(range);
+                match random(4) {
+                    0 | 1 => {
+                        let index = tree.insert(value);
+                        assert_eq!(index.is_some(), set.insert(value));
+                        if let Some(index) = index {
+                            indices.insert(value, index);
+                        }
+                    }
+                    2 => {
+                        assert_eq!(tree.remove(value), set.take(&value));
+                        indices.remove(&value);
+                    }
+                    _ => assert_eq!(tree.contains(value), indices.get(&value).copied()),
+                }
+
+                assert_eq!(tree.validate(), Ok(()));
+                assert_eq!(tree.len(), set.len());
+            }
+
+            assert!(tree.iter().eq(set.iter()));
+            for (value, &index) in &indices {
+                assert_eq!(tree.get(index), Some(value));
+            }
+        }
+    }
+
+    #[test]
+    fn panic_safety() {
+        use std::cell::Cell;
+        use std::cmp::Ordering;
+        use std::panic::{self, AssertUnwindSafe};
+
+        thread_local!(static COMPARISONS_LEFT: Cell<usize> = const { Cell::new(usize::MAX) });
+
+        // Panics once COMPARISONS_LEFT runs out.
+        #[derive(Debug, PartialEq, Eq)]
+        struct Fragile(u32);
+
+        impl Ord for Fragile {
+            fn cmp(&self, other: &Self) -> Ordering {
+                let left = COMPARISONS_LEFT.get();
+                assert!(left > 0, "comparison failed");
+                COMPARISONS_LEFT.set(left - 1);
+                self.0.cmp(&other.0)
+            }
+        }
+
+        impl PartialOrd for Fragile {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        let mut tree: binary_search::Tree<Fragile> =
+            binary_search::Tree::from_sorted_unchecked((0..100).map(Fragile).collect());
+
+        for limit in 0..8 {
+            COMPARISONS_LEFT.set(limit);
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| tree.remove(Fragile(37))));
+            COMPARISONS_LEFT.set(limit);
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| tree.insert(Fragile(37))));
+
+            COMPARISONS_LEFT.set(usize::MAX);
+            assert_eq!(tree.validate(), Ok(()));
+            assert_eq!(tree.len(), 100);
+        }
+    }
+
+    #[test]
+    fn shape() {
+        let mut ascending = binary_search::Tree::default();
+        let mut descending = binary_search::Tree::default();
+        for n in 0..7 {
+            ascending.insert(n);
+            descending.insert(6 - n);
+        }
+
+        // Both end up perfectly balanced.
+        let balanced: binary_search::Tree<u32, u8> =
+            binary_search::Tree::from_sorted_unchecked((10..17).collect());
+        assert!(ascending.shape_eq(&descending));
+        assert!(ascending.shape_eq(&balanced));
+        assert_eq!(ascending.fingerprint(), descending.fingerprint());
+
+        descending.remove(0);
+        assert!(!ascending.shape_eq(&descending));
+        assert_ne!(ascending.fingerprint(), descending.fingerprint());
+        assert!(binary_search::Tree::<u8>::new().shape_eq(&binary_search::Tree::<u8>::new()));
+    }
+
+    #[test]
+    fn interval_tree() {
+        let mut tree = binary_search::IntervalTree::new();
+        assert_eq!(tree.insert(0..10, 'a'), None);
+        assert_eq!(tree.insert(5..8, 'b'), None);
+        assert_eq!(tree.insert(12..20, 'c'), None);
+        assert_eq!(tree.insert(9..9, 'd'), None);
+        assert_eq!(tree.insert(5..8, 'e'), Some('b'));
+        assert_eq!(tree.len(), 4);
+
+        let overlapping = tree.find_overlapping(&(7..13));
+        assert_eq!(
+            overlapping,
+            [(&(0..10), &'a'), (&(5..8), &'e'), (&(12..20), &'c')]
+        );
+        assert!(tree.find_overlapping(&(10..12)).is_empty());
+        assert_eq!(tree.stab(&9), [(&(0..10), &'a')]);
+        assert_eq!(tree.stab(&12), [(&(12..20), &'c')]);
+
+        assert_eq!(tree.remove(&(0..10)), Some('a'));
+        assert_eq!(tree.remove(&(0..10)), None);
+        assert!(tree.stab(&9).is_empty());
+        assert_eq!(tree.get(&(5..8)), Some(&'e'));
+    }
+
+    #[test]
+    fn augmented_tree() {
+        use binary_search::{Count, Max, Sum};
+
+        let mut tree: binary_search::AugmentedTree<u32, (Sum<u32>, Max<u32>, Count)> =
+            binary_search::Tree::new();
+        for n in 1..=100 {
+            tree.insert(n);
+        }
+        tree.remove(50);
+
+        assert_eq!(tree.augment(), Some(&(Sum(5000), Max(100), Count(99))));
+        assert_eq!(tree.query_range(..=10), Some((Sum(55), Max(10), Count(10))));
+        assert_eq!(
+            tree.query_range(45..55),
+            Some((Sum(445), Max(54), Count(9)))
+        );
+        assert_eq!(tree.query_range(101..), None);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn select() {
+        use binary_search::{Count, Rank};
+
+        let mut tree: binary_search::AugmentedTree<u32, Count> = binary_search::Tree::new();
+        for n in (0..2000).rev() {
+            tree.insert(n * 2);
+        }
+        for n in 0..100 {
+            tree.remove(n * 2);
+        }
+
+        assert_eq!(tree.select(0), Some(&200));
+        assert_eq!(tree[Rank(1000)], 2200);
+        assert_eq!(tree.select(1899), Some(&3998));
+        assert_eq!(tree.select(1900), None);
+
+        let page: Vec<u32> = (1000..1005).map(|k| tree[Rank(k)]).collect();
+        assert_eq!(page, [2200, 2202, 2204, 2206, 2208]);
+    }
+
+    #[test]
+    fn persistent_tree() {
+        use binary_search::persistent;
+
+        let empty = persistent::Tree::new();
+        let first = (0..100).fold(empty.clone(), |tree, n| tree.insert(n));
+        let second = first.remove(&50).insert(200);
+
+        assert!(empty.is_empty());
+        assert_eq!(first.len(), 100);
+        assert!(first.contains(&50) && !first.contains(&200));
+        assert!(!second.contains(&50) && second.contains(&200));
+        assert!(first.iter().copied().eq(0..100));
+
+        // Nothing changes, so the versions are shared.
+        assert!(first.insert(10).ptr_eq(&first));
+        assert!(first.remove(&1000).ptr_eq(&first));
+        assert!(!second.ptr_eq(&first));
+    }
+
+    #[test]
+    #[cfg(feature = "concurrent")]
+    fn concurrent_tree() {
+        use std::thread;
+
+        let tree = binary_search::concurrent::Tree::new();
+        thread::scope(|scope| {
+            for writer in 0..4 {
+                let tree = &tree;
+                scope.spawn(move || {
+                    for n in 0..250 {
+                        assert!(tree.insert(writer * 250 + n));
+                    }
+                });
+            }
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    // Every version a reader sees is complete.
+                    let version = tree.load();
+                    assert!(version.iter().is_sorted());
+                });
+            }
+        });
+
+        assert_eq!(tree.len(), 1000);
+        assert!(tree.remove(&10) && !tree.remove(&10));
+        assert_eq!(tree.range(8..13), [8, 9, 11, 12]);
+        assert!(tree.contains(&999) && !tree.contains(&10));
+    }
+
+    #[test]
+    fn red_black_tree() {
+        let mut avl = binary_search::Tree::default();
+        let mut red_black = binary_search::RbTree::default();
+
+        // Both trees agree on the same operations.
+        for n in (0..1000).map(|n| n * 7 % 1000) {
+            assert_eq!(avl.insert(n).is_some(), red_black.insert(n).is_some());
+        }
+        for n in (0..1000).step_by(3) {
+            assert_eq!(avl.remove(n), red_black.remove(n));
+        }
+        assert_eq!(red_black.insert(1), None);
+        assert_eq!(red_black.remove(3), None);
+
+        assert_eq!(avl.len(), red_black.len());
+        assert!(avl.iter().eq(red_black.iter()));
+        let index = red_black.contains(500).unwrap();
+        assert_eq!(red_black.get(index), Some(&500));
+        assert_eq!(red_black.validate(), Ok(()));
+    }
+
+    #[test]
+    fn weight_balanced_tree() {
+        use binary_search::Rank;
+
+        let mut tree = binary_search::WbTree::default();
+        for n in 0..1000 {
+            tree.insert(n * 2);
+        }
+        for n in 0..100 {
+            assert_eq!(tree.remove(n * 2), Some(n * 2));
+        }
+        assert_eq!(tree.insert(500), None);
+
+        assert_eq!(tree.len(), 900);
+        assert_eq!(tree.select(0), Some(&200));
+        assert_eq!(tree[Rank(450)], 1100);
+        assert_eq!(tree.rank(&1100), 450);
+        assert_eq!(tree.rank(&1101), 451);
+        assert_eq!(tree.select(900), None);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn treap() {
+        let mut treap = binary_search::Treap::default();
+        for n in 0..1000 {
+            treap.insert(n);
+        }
+        assert_eq!(treap.insert(10), None);
+        assert_eq!(treap.remove(10), Some(10));
+
+        let mut upper = treap.split_off(&600);
+        assert_eq!(treap.len(), 599);
+        assert_eq!(upper.len(), 400);
+        assert!(upper.iter().copied().eq(600..1000));
+
+        // Joining only works with every value of the other treap larger.
+        let lower = upper.concat_disjoint(treap).unwrap_err();
+        let mut treap = lower;
+        treap.concat_disjoint(upper).ok().unwrap();
+        assert_eq!(treap.len(), 999);
+
+        assert_eq!(treap.validate(), Ok(()));
+        let index = treap.contains(999).unwrap();
+        assert_eq!(treap.get(index), Some(&999));
+    }
+
+    #[test]
+    fn scapegoat_tree() {
+        let mut tree = binary_search::ScapegoatTree::default();
+        // Sorted insertions make the deepest paths, so they get rebuilt.
+        for n in 0..1000 {
+            tree.insert(n);
+        }
+        assert_eq!(tree.validate(), Ok(()));
+        assert_eq!(tree.insert(10), None);
+
+        for n in (0..1000).step_by(2) {
+            assert_eq!(tree.remove(n), Some(n));
+        }
+        assert_eq!(tree.len(), 500);
+        assert!(tree.iter().copied().eq((1..1000).step_by(2)));
+
+        assert_eq!(tree.validate(), Ok(()));
+        let index = tree.contains(999).unwrap();
+        assert_eq!(tree.get(index), Some(&999));
+    }
+
+    #[test]
+    fn ordered_set() {
+        use binary_search::OrderedSet;
+
+        // The same code runs against every backend.
+        fn fill<S: OrderedSet<u32>>(mut set: S) -> Vec<u32> {
+            for n in (0..100).rev() {
+                set.insert(n);
+            }
+            assert_eq!(set.insert(50), None);
+            assert_eq!(set.remove(50), Some(50));
+            assert_eq!(set.len(), 99);
+            assert!(set.contains(49).is_some());
+            set.range(45..=55).copied().collect()
+        }
+
+        let expected = vec![45, 46, 47, 48, 49, 51, 52, 53, 54, 55];
+        assert_eq!(fill(binary_search::Tree::<u32>::new()), expected);
+        assert_eq!(fill(binary_search::RbTree::<u32>::new()), expected);
+        assert_eq!(fill(binary_search::WbTree::<u32>::new()), expected);
+        assert_eq!(fill(binary_search::Treap::<u32>::new()), expected);
+        assert_eq!(fill(binary_search::ScapegoatTree::<u32>::new()), expected);
+    }
+
+    #[test]
+    fn cursor_mut() {
+        let mut tree: binary_search::Tree<u32> =
+            (0..100).map(|n| n * 10).collect::<Vec<_>>().into();
+        let mut cursor = tree.cursor_mut_at(&415);
+        assert_eq!(cursor.current(), Some(&420));
+        assert_eq!(cursor.peek_prev(), Some(&410));
+
+        assert!(cursor.insert_before(415).is_ok());
+        assert_eq!(cursor.insert_after(410), Err(410));
+        assert_eq!(cursor.remove_current(), Some(420));
+        assert_eq!(cursor.current(), Some(&430));
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&415));
+
+        // Past the largest value, the cursor wraps around to the smallest.
+        let mut cursor = tree.cursor_mut_at(&1000);
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&0));
+
+        assert_eq!(tree.len(), 100);
+        assert_eq!(tree.validate(), Ok(()));
+    }
+
+    #[test]
+    fn cursor() {
+        use std::ops::Bound;
+
+        let tree: binary_search::Tree<u32> = (0..100).map(|n| n * 10).collect::<Vec<_>>().into();
+        let mut cursor = tree.lower_bound(Bound::Excluded(&420));
+        assert_eq!(cursor.current(), Some(&430));
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&410));
+        assert_eq!(cursor.peek_next(), Some(&420));
+
+        let cursor = tree.upper_bound(Bound::Included(&425));
+        assert_eq!(cursor.current(), Some(&420));
+        assert_eq!(tree.upper_bound(Bound::Excluded(&0)).current(), None);
+
+        let mut cursor = tree.cursor_back();
+        assert_eq!(cursor.current(), Some(&990));
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(tree.cursor_front().current(), Some(&0));
+    }
+
+    #[test]
+    fn range_map() {
+        use binary_search::range_map::RangeMap;
+
+        let mut map = RangeMap::new();
+        map.insert(0..10, 'a');
+        map.insert(20..30, 'a');
+        // Cuts 'a' in two, leaving 0..5 and 8..10.
+        map.insert(5..8, 'b');
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get(&6), Some(&'b'));
+        assert_eq!(map.get(&9), Some(&'a'));
+        assert_eq!(map.get(&15), None);
+
+        // Touching ranges with equal values are joined.
+        map.insert(10..20, 'a');
+        assert_eq!(map.get_key_value(&15), Some((&(8..30), &'a')));
+
+        map.remove(4..25);
+        let ranges: Vec<_> = map.iter().map(|(range, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..4, 25..30]);
+    }
+
+    #[test]
+    fn tree_queue() {
+        use binary_search::queue::TreeQueue;
+
+        let mut queue = TreeQueue::new();
+        let a = queue.push(50);
+        let b = queue.push(20);
+        let c = queue.push(80);
+        queue.push(20);
+
+        // Decreasing a key past its neighbors moves it to a new handle.
+        let a = queue.update(a, 10).unwrap();
+        assert_eq!(queue.get(a), Some(&10));
+        assert_eq!(queue.remove(c), Some(80));
+        assert_eq!(queue.get(c), None);
+        assert_eq!(queue.update(c, 5), Err(5));
+
+        assert_eq!(queue.pop_max(), Some(20));
+        assert_eq!(queue.pop_min(), Some(10));
+        assert_eq!(queue.get(b), Some(&20));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn sorted_list() {
+        let mut list: binary_search::SortedList<u32> = [50, 10, 40, 20, 30].into_iter().collect();
+        assert_eq!(list.insert(25), 2);
+        // Equal values go after the ones already there.
+        assert_eq!(list.insert(25), 3);
+        assert_eq!(list[4], 30);
+
+        assert_eq!(list.binary_search(&40), Ok(5));
+        assert_eq!(list.binary_search(&45), Err(6));
+        assert_eq!(list.remove_by_rank(0), Some(10));
+        assert!(list.range_by_rank(1..4).copied().eq([25, 25, 30]));
+        assert_eq!(list.get_by_rank(6), None);
+    }
+
+    #[test]
+    fn lru_cache() {
+        use binary_search::cache::{Cache, LruCache};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let mut cache = LruCache::new(2);
+        let sink = Rc::clone(&evicted);
+        cache.set_on_evict(move |key, _| sink.borrow_mut().push(key));
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        // Using "a" leaves "b" as the least recently used.
+        assert_eq!(cache.get(&"a"), Some(&1));
+        cache.insert("c", 3);
+        assert_eq!(*evicted.borrow(), ["b"]);
+        assert_eq!(cache.peek(&"b"), None);
+        assert_eq!(cache.pop_lru(), Some(("a", 1)));
+
+        // Scores as expiry times.
+        let mut cache = Cache::new(10);
+        cache.insert("x", 'x', 30);
+        cache.insert("y", 'y', 10);
+        assert_eq!(cache.pop_expired(&20), Some(("y", 'y', 10)));
+        assert_eq!(cache.pop_expired(&20), None);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "dot")]
+    fn to_dot() {
+        let mut tree: binary_search::Tree<&str> = binary_search::Tree::new();
+        let b = tree.insert("b").unwrap();
+        let a = tree.insert("a").unwrap();
+        tree.insert("say \"c\"");
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains(&format!("n{b} -> n{a};")));
+        assert!(dot.contains("label=\"say \\\"c\\\"\\nindex 2, height 0\""));
+    }
+
+    #[test]
+    fn render() {
+        let mut tree: binary_search::Tree<u32> = binary_search::Tree::new();
+        assert_eq!(tree.render(), "");
+        for value in [4, 2, 6, 1, 3, 5] {
+            tree.insert(value);
+        }
+
+        let drawing = "/-- 6
+|   \\-- 5
+4
+|   /-- 3
+\\-- 2
+    \\-- 1
+";
+        assert_eq!(tree.render(), drawing);
+    }
+
+    #[test]
+    fn snapshot() {
+        use binary_search::{SnapshotError, Tree};
+
+        let encode = |value: &u32, bytes: &mut Vec<u8>| bytes.extend(value.to_le_bytes());
+        let decode = |bytes: &[u8]| bytes.try_into().ok().map(u32::from_le_bytes);
+
+        let mut tree: Tree<u32> = (0..100).collect::<Vec<_>>().into();
+        for value in (0..100).step_by(3) {
+            tree.remove(value);
+        }
+        let bytes = tree.to_bytes(encode);
+        let loaded = Tree::<u32>::from_bytes(&bytes, decode).unwrap();
+        assert!(loaded.validate().is_ok());
+        assert!(loaded.iter().eq(tree.iter()));
+
+        let empty = Tree::<u32>::new().to_bytes(encode);
+        assert!(Tree::<u32>::from_bytes(&empty, decode).unwrap().is_empty());
+
+        let words: Tree<String> = vec!["pear".to_string(), "fig".to_string()].into();
+        let bytes = words.to_bytes(|word, bytes| bytes.extend(word.as_bytes()));
+        let loaded =
+            Tree::<String>::from_bytes(&bytes, |b| String::from_utf8(b.to_vec()).ok()).unwrap();
+        assert!(loaded.iter().eq(words.iter()));
+
+        let bytes = Tree::<u32>::from([1, 2, 3]).to_bytes(encode);
+        let load = |bytes: &[u8]| Tree::<u32>::from_bytes(bytes, decode).err();
+        assert_eq!(
+            load(&bytes[..bytes.len() - 1]),
+            Some(SnapshotError::Truncated)
+        );
+        assert_eq!(
+            load(&[bytes.as_slice(), &[0]].concat()),
+            Some(SnapshotError::Length)
+        );
+        assert_eq!(load(b"nope"), Some(SnapshotError::Magic));
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 9;
+        assert_eq!(load(&wrong_version), Some(SnapshotError::Version(9)));
+
+        let reversed = Tree::<u32>::from_bytes(&bytes, |b| decode(b).map(|v| 10 - v));
+        assert_eq!(reversed.err(), Some(SnapshotError::Order(0)));
+        let picky = Tree::<u32>::from_bytes(&bytes, |b| decode(b).filter(|&v| v != 3));
+        assert_eq!(picky.err(), Some(SnapshotError::Value(2)));
+    }
+
+    #[test]
+    fn tree_view() {
+        use binary_search::{Tree, TreeView};
+
+        let tree: Tree<u32> = (0..100).map(|n| n * 2).collect::<Vec<_>>().into();
+        let bytes = tree.to_bytes(|value, bytes| bytes.extend(value.to_le_bytes()));
+        let view = TreeView::new(&bytes, |b| u32::from_le_bytes(b.try_into().unwrap())).unwrap();
+
+        assert_eq!(view.len(), 100);
+        assert!(view.contains(&42));
+        assert!(!view.contains(&43));
+        assert_eq!(view.get(&198), Some(198));
+        assert!(view.range(..).eq(tree.iter().copied()));
+        assert!(view.range(15..=21).eq([16, 18, 20]));
+        assert_eq!(view.range(300..).next(), None);
+
+        let words: Tree<String> = vec!["fig".to_string(), "kiwi".to_string()].into();
+        let bytes = words.to_bytes(|word, bytes| bytes.extend(word.as_bytes()));
+        let view = TreeView::new(&bytes, |b| std::str::from_utf8(b).unwrap()).unwrap();
+        assert_eq!(view.get(&"kiwi"), Some("kiwi"));
+        assert!(view.range("g"..).eq(["kiwi"]));
+
+        let empty = Tree::<u32>::new().to_bytes(|_, _| {});
+        assert!(TreeView::new(&empty, |_| 0)
+            .unwrap()
+            .range(..)
+            .next()
+            .is_none());
+        assert!(TreeView::new(&bytes[..10], |_| 0).is_err());
+    }
+
+    #[test]
+    fn incremental_fingerprint() {
+        use binary_search::{Count, Fingerprint, Tree};
+
+        let mut tree: Tree<u32, u32, Fingerprint> = Tree::new();
+        let mut replica: Tree<u32, u32, (Count, Fingerprint)> = Tree::new();
+        assert_eq!(tree.query_range(..), None);
+
+        for value in [5, 3, 8, 1] {
+            tree.insert(value);
+        }
+        for value in [1, 8, 3, 5, 9] {
+            replica.insert(value);
+        }
+        assert_ne!(
+            tree.query_range(..).map(|n| n.0),
+            Some(replica.fingerprint())
+        );
+
+        replica.remove(9);
+        let Some(Fingerprint(fingerprint)) = tree.query_range(..) else {
+            panic!("tree is empty")
+        };
+        assert_eq!(fingerprint, tree.fingerprint());
+        assert_eq!(replica.query_range(..).map(|(_, n)| n.0), Some(fingerprint));
+
+        // Fingerprints don't change between builds, so they can be kept.
+        let tree: Tree<u32> = crate::avl![1, 2, 3];
+        assert_eq!(tree.fingerprint(), 0x2765_bb8f_85d5_b501);
+    }
+
+    #[test]
+    fn merge_all() {
+        use binary_search::{DuplicatePolicy, Tree};
+
+        // Ordered by the first field only, so it's visible which tree an
+        // equal value came from.
+        #[derive(Debug)]
+        struct Tagged(u32, char);
+        impl PartialEq for Tagged {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+        impl Eq for Tagged {}
+        impl PartialOrd for Tagged {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Tagged {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let shards = || {
+            [
+                (&[1, 4, 7][..], 'a'),
+                (&[2, 4, 8], 'b'),
+                (&[], 'c'),
+                (&[4, 9, 1], 'd'),
+            ]
+            .into_iter()
+            .map(|(values, tag)| {
+                values
+                    .iter()
+                    .map(|&v| Tagged(v, tag))
+                    .collect::<Vec<_>>()
+                    .into()
+            })
+            .collect::<Vec<Tree<Tagged>>>()
+        };
+        let tags = |tree: &Tree<Tagged>| tree.iter().map(|t| (t.0, t.1)).collect::<Vec<_>>();
+
+        let kept = Tree::merge_all(shards(), DuplicatePolicy::Reject);
+        assert!(kept.validate().is_ok());
+        assert_eq!(
+            tags(&kept),
+            [(1, 'a'), (2, 'b'), (4, 'a'), (7, 'a'), (8, 'b'), (9, 'd')]
+        );
+
+        let replaced = Tree::merge_all(shards(), DuplicatePolicy::ReplaceExisting);
+        assert_eq!(tags(&replaced)[..3], [(1, 'd'), (2, 'b'), (4, 'd')]);
+
+        let all = Tree::merge_all(shards(), DuplicatePolicy::KeepBoth);
+        assert!(all.validate().is_ok());
+        assert_eq!(all.len(), 9);
+        assert_eq!(all.duplicate_policy(), DuplicatePolicy::KeepBoth);
+        assert_eq!(tags(&all)[3..6], [(4, 'a'), (4, 'b'), (4, 'd')]);
+
+        assert!(Tree::<u32>::merge_all(Vec::new(), DuplicatePolicy::Reject).is_empty());
+    }
+
+    #[test]
+    fn diff() {
+        use binary_search::{DiffItem, Tree};
+
+        let local: Tree<u32> = Tree::from([1, 3, 5, 7]);
+        let remote: Tree<u32, u16> = [3, 4, 7, 8].into_iter().fold(Tree::new(), |mut tree, v| {
+            tree.insert(v);
+            tree
+        });
+
+        let (mut only_local, mut only_remote, mut common) = (vec![], vec![], vec![]);
+        for item in local.diff(&remote) {
+            match item {
+                DiffItem::Left(&v) => only_local.push(v),
+                DiffItem::Right(&v) => only_remote.push(v),
+                DiffItem::Both(&v, _) => common.push(v),
+            }
+        }
+        assert_eq!(only_local, [1, 5]);
+        assert_eq!(only_remote, [4, 8]);
+        assert_eq!(common, [3, 7]);
+
+        let empty = Tree::<u32>::new();
+        assert!(empty.diff(&empty).next().is_none());
+        assert!(local
+            .diff(&empty)
+            .all(|item| matches!(item, DiffItem::Left(_))));
+    }
+
+    #[test]
+    fn transaction() {
+        use binary_search::Tree;
+
+        let mut tree: Tree<u32> = Tree::from([10, 20, 30, 40]);
+        let handle = tree.handle(1).unwrap();
+        let before: Vec<_> = (0..4).map(|n| tree.get(n).copied()).collect();
+
+        let result: Result<(), &str> = tree.transaction(|txn| {
+            assert!(txn.remove(20));
+            assert!(txn.remove(40));
+            txn.insert(25);
+            txn.insert(35);
+            assert!(txn.contains(25).is_some());
+            Err("rejected")
+        });
+        assert_eq!(result, Err("rejected"));
+        assert!(tree.validate().is_ok());
+        assert_eq!(
+            (0..4).map(|n| tree.get(n).copied()).collect::<Vec<_>>(),
+            before
+        );
+        assert_eq!(tree.get_handle(handle), Some(&20));
+        assert_eq!(tree.len(), 4);
+
+        let result: Result<usize, ()> = tree.transaction(|txn| {
+            txn.remove(10);
+            Ok(txn.insert(15).unwrap())
+        });
+        assert!(result.is_ok());
+        assert!(tree.iter().eq(&[15, 20, 30, 40]));
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _: Result<(), ()> = tree.transaction(|txn| {
+                txn.remove(30);
+                panic!("validation failed");
+            });
+        }));
+        assert!(panicked.is_err());
+        assert!(tree.validate().is_ok());
+        assert!(tree.iter().eq(&[15, 20, 30, 40]));
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn stats() {
+        use binary_search::{Stats, Tree};
+
+        let mut tree: Tree<u32> = Tree::new();
+        for value in [1, 2, 3, 10, 8] {
+            tree.insert(value);
+        }
+        tree.remove(2);
+        tree.insert(0);
+
+        let stats = tree.stats();
+        assert_eq!(stats.single_rotations, 1);
+        assert_eq!(stats.double_rotations, 1);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.allocations, 5);
+        assert_eq!(stats.free_list_hits, 1);
+        assert!(stats.comparisons > 0);
+
+        tree.reset_stats();
+        assert_eq!(tree.stats(), Stats::default());
+        tree.contains(8);
+        assert_eq!(tree.stats().comparisons, 2);
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn insert_hint_appends() {
+        let mut tree: binary_search::Tree<u32> = binary_search::Tree::new();
+        let mut hint = 0;
+        for value in 0..1000 {
+            hint = tree.insert_hint(hint, value).unwrap();
+        }
+        tree.reset_stats();
+
+        // Each value is compared with the hint alone, not searched for.
+        for value in 1000..2000 {
+            hint = tree.insert_hint(hint, value).unwrap();
+        }
+        assert_eq!(tree.stats().comparisons, 1000);
+        assert!(tree.iter().copied().eq(0..2000));
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn stats_after_concat() {
+        use binary_search::Tree;
+
+        // Both when the tree is empty and when it's the smaller one, it
+        // keeps its own counters rather than taking OTHER's.
+        for len in [0, 3] {
+            let mut tree: Tree<u32> = Tree::new();
+            for value in 0..len {
+                tree.insert(value);
+            }
+            let allocations = tree.stats().allocations;
+
+            let mut other: Tree<u32> = Tree::new();
+            for value in 10..50 {
+                other.insert(value);
+            }
+            assert!(tree.concat_disjoint(other).is_ok());
+            assert_eq!(tree.stats().allocations, allocations);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "ffi")]
+    fn ffi() {
+        use crate::ffi::*;
+        use std::ffi::{c_int, c_void};
+
+        unsafe {
+            let set = avl_i64_new();
+            for value in [5, -3, 9, 5] {
+                avl_i64_insert(set, value);
+            }
+            assert!(avl_i64_remove(set, 9));
+            assert!(!avl_i64_contains(set, 9));
+            assert_eq!(avl_i64_len(set), 2);
+
+            let iter = avl_i64_iter(set);
+            let (mut value, mut values) = (0, vec![]);
+            while avl_i64_iter_next(iter, &mut value) {
+                values.push(value);
+            }
+            assert_eq!(values, [-3, 5]);
+            avl_i64_iter_free(iter);
+            avl_i64_free(set);
+        }
+
+        // Orders strings by length.
+        extern "C" fn by_len(a: *const c_void, b: *const c_void) -> c_int {
+            let len = |p: *const c_void| unsafe { &*p.cast::<&str>() }.len();
+            len(a).cmp(&len(b)) as c_int
+        }
+        let words = ["pear", "fig", "banana", "kiwi"];
+        let ptr = |n: usize| std::ptr::from_ref(&words[n]).cast::<c_void>();
+
+        unsafe {
+            let set = avl_ptr_new(by_len);
+            for n in 0..words.len() {
+                avl_ptr_insert(set, ptr(n));
+            }
+            assert_eq!(avl_ptr_len(set), 3);
+            assert!(avl_ptr_contains(set, ptr(3)));
+            assert_eq!(avl_ptr_remove(set, ptr(2)), ptr(2));
+
+            let iter = avl_ptr_iter(set);
+            let mut found = vec![];
+            loop {
+                let next = avl_ptr_iter_next(iter);
+                if next.is_null() {
+                    break;
+                }
+                found.push(*next.cast::<&str>());
+            }
+            assert_eq!(found, ["fig", "pear"]);
+            avl_ptr_iter_free(iter);
+            avl_ptr_free(set);
+        }
+    }
+
+    #[test]
+    fn health() {
+        use binary_search::Tree;
+
+        let mut tree: Tree<u32> = Tree::new();
+        assert_eq!(tree.health().depths, []);
+        assert_eq!(tree.health().average_depth, 0.0);
+
+        for value in 1..=7 {
+            tree.insert(value);
+        }
+        let health = tree.health();
+        assert_eq!(health.depths, [1, 2, 4]);
+        assert!((health.average_depth - 10.0 / 7.0).abs() < 1e-9);
+        assert_eq!((health.len, health.slots, health.vacant), (7, 7, 0));
+
+        tree.remove(2);
+        tree.remove(5);
+        let health = tree.health();
+        assert_eq!(health.depths.iter().sum::<usize>(), 5);
+        assert_eq!(health.vacant, health.slots - 5);
+
+        tree.compact();
+        tree.shrink_to_fit();
+        let health = tree.health();
+        assert_eq!((health.slots, health.vacant), (5, 0));
+        assert_eq!(health.wasted_bytes, 0);
+    }
+
+    #[test]
+    fn avl_macro() {
+        use binary_search::Tree;
+
+        let tree = crate::avl![3, 1, 2, 3,];
+        assert_eq!(tree.len(), 3);
+        assert!(tree.iter().eq(&[1, 2, 3]));
+
+        let tree: Tree<u32> = crate::avl![];
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn into_sorted_vec() {
+        let mut tree = crate::avl![5, 3, 8, 1];
+        tree.remove(3);
+        tree.insert(4);
+        assert_eq!(tree.to_vec(), [1, 4, 5, 8]);
+        assert_eq!(tree.into_sorted_vec(), [1, 4, 5, 8]);
+    }
+
+    #[test]
+    fn get_many_mut() {
+        use binary_search::GetManyMutError;
+
+        let mut tree = crate::avl![10, 20, 30];
+        let low = tree.contains(10).unwrap();
+        let high = tree.contains(30).unwrap();
+
+        let [a, b] = tree.get_many_mut([high, low]).unwrap();
+        *a += 1;
+        *b -= 1;
+        assert_eq!(tree.to_vec(), [9, 20, 31]);
+
+        assert_eq!(
+            tree.get_many_mut([low, high, low]).err(),
+            Some(GetManyMutError::Duplicate(low)),
+        );
+        tree.remove(20);
+        let vacant = (0..3).find(|&n| !tree.is_occupied(n)).unwrap();
+        assert_eq!(
+            tree.get_many_mut([low, vacant]).err(),
+            Some(GetManyMutError::Vacant(vacant)),
+        );
+    }
+
+    #[test]
+    fn map() {
+        use binary_search::Tree;
+
+        let mut ids: Tree<u32> = crate::avl![4, 1, 3, 2];
+        ids.remove(3);
+        let index = ids.contains(4).unwrap();
+
+        let names = ids.clone().map(|id| format!("user-{id}"));
+        assert!(names.validate().is_ok());
+        assert_eq!(names.get(index).map(String::as_str), Some("user-4"));
+        assert_eq!(names.to_vec(), ["user-1", "user-2", "user-4"]);
+
+        let halves = ids.filter_map(|id| (id != 1).then_some(5 - id / 2));
+        assert!(halves.validate().is_ok());
+        assert_eq!(halves.to_vec(), [3, 4]);
+    }
+
+    #[test]
+    fn fold() {
+        let mut tree = crate::avl![5, 2, 9, 7, 1];
+        tree.remove(9);
+        assert_eq!(tree.fold(0, |sum, value| sum * 10 + value), 1257);
+
+        let mut values = vec![];
+        tree.for_each_in_order(|&value| values.push(value));
+        assert_eq!(values, [1, 2, 5, 7]);
+
+        let empty: binary_search::Tree<u32> = crate::avl![];
+        assert_eq!(empty.fold(3, |sum, value| sum + value), 3);
+    }
+
+    #[test]
+    fn remove_with_index() {
+        let mut tree = crate::avl![3, 1, 2];
+        let index = tree.contains(2).unwrap();
+        assert_eq!(tree.remove_with_index(2), Some((index, 2)));
+        assert!(!tree.is_occupied(index));
+        assert_eq!(tree.remove_with_index(2), None);
+    }
+
+    #[test]
+    fn iter_from() {
+        let tree = crate::avl![10, 20, 30, 40];
+        assert!(tree.iter_from(&20).eq(&[20, 30, 40]));
+        assert!(tree.iter_from(&25).eq(&[30, 40]));
+        assert!(tree.iter_from(&0).eq(&[10, 20, 30, 40]));
+        assert_eq!(tree.iter_from(&41).next(), None);
+    }
+
+    #[test]
+    fn next_index() {
+        let mut tree = crate::avl![1, 2, 3, 4];
+        tree.remove(3);
+        let two = tree.contains(2).unwrap();
+        let four = tree.next_index(two).unwrap();
+        assert_eq!(tree.get(four), Some(&4));
+        assert_eq!(tree.prev_index(four), Some(two));
+        assert_eq!(tree.next_index(four), None);
+
+        let one = tree.prev_index(two).unwrap();
+        assert_eq!(tree.prev_index(one), None);
+        tree.remove(1);
+        assert_eq!(tree.next_index(one), None);
+    }
+
+    #[test]
+    fn iter_subtree() {
+        use binary_search::{Count, Tree};
+
+        let mut tree: Tree<u32, u32, Count> = Tree::new();
+        for value in 1..=7 {
+            tree.insert(value);
+        }
+        // 4 is the root, with 2 and 6 below it.
+        let two = tree.contains(2).unwrap();
+        assert!(tree.iter_subtree(two).eq(&[1, 2, 3]));
+        assert_eq!(tree.subtree_len(two), 3);
+
+        let four = tree.contains(4).unwrap();
+        assert_eq!(tree.iter_subtree(four).count(), 7);
+        assert_eq!(tree.subtree_len(four), 7);
+
+        let seven = tree.contains(7).unwrap();
+        assert!(tree.iter_subtree(seven).eq(&[7]));
+        tree.remove(7);
+        assert_eq!(tree.iter_subtree(seven).next(), None);
+        assert_eq!(tree.subtree_len(seven), 0);
+    }
+
+    #[test]
+    fn extend_from_sorted() {
+        use binary_search::{DuplicatePolicy, ShrinkPolicy, Tree};
+
+        // Few values are inserted one by one, keeping indices.
+        let mut tree: Tree<u32> = (0..100).step_by(2).collect::<Vec<_>>().into();
+        let index = tree.contains(50).unwrap();
+        tree.extend_from_sorted([5, 51]);
+        assert_eq!(tree.contains(50), Some(index));
+        assert_eq!(tree.len(), 52);
+
+        // Many values are merged in, and the tree is rebuilt.
+        let handle = tree.handle(index).unwrap();
+        tree.extend_from_sorted(0..200);
+        assert!(tree.validate().is_ok());
+        assert!(tree.iter().copied().eq(0..200));
+        assert_eq!(tree.get_handle(handle), None);
+        assert_eq!(
+            tree.get_handle(tree.handle(index).unwrap()),
+            tree.get(index)
+        );
+
+        // Unsorted values are still inserted.
+        let mut tree: Tree<u32> = Tree::new();
+        tree.extend_from_sorted([3, 1, 2]);
+        assert_eq!(tree.to_vec(), [1, 2, 3]);
+
+        let mut tree: Tree<u32> = Tree::new();
+        tree.set_duplicate_policy(DuplicatePolicy::KeepBoth);
+        tree.insert(2);
+        tree.extend_from_sorted([1, 2, 2, 3]);
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.to_vec(), [1, 2, 2, 2, 3]);
+        assert_eq!(tree.duplicate_policy(), DuplicatePolicy::KeepBoth);
+
+        // Under `Never`, every value keeps its index.
+        let mut tree: Tree<u32> = Tree::from([4, 8]);
+        tree.set_shrink_policy(ShrinkPolicy::Never);
+        let index = tree.contains(8).unwrap();
+        tree.extend_from_sorted(0..100);
+        assert_eq!(tree.get(index), Some(&8));
+        assert!(tree.iter().copied().eq(0..100));
+    }
+
+    #[test]
+    fn union() {
+        use binary_search::{Count, Tree};
+
+        let left = crate::avl![1, 3, 5, 7];
+        let mut right: Tree<i32, u32, Count> = Tree::new();
+        for value in [2, 3, 4, 7, 9] {
+            right.insert(value);
+        }
+
+        assert!(left.union_iter(&right).eq(&[1, 2, 3, 4, 5, 7, 9]));
+        let union = left.union(&right);
+        assert!(union.validate().is_ok());
+        assert_eq!(union.to_vec(), [1, 2, 3, 4, 5, 7, 9]);
+
+        let union = left.into_union(crate::avl![0, 5, 8]);
+        assert!(union.validate().is_ok());
+        assert_eq!(union.to_vec(), [0, 1, 3, 5, 7, 8]);
+    }
+
+    #[test]
+    fn intersection() {
+        use binary_search::{DuplicatePolicy, Tree};
+
+        let left: Tree<u32> = (0..1000).collect::<Vec<_>>().into();
+        let right = crate::avl![3, 500, 999, 1000];
+        // Few values on one side make it search rather than step.
+        assert!(left.intersection_iter(&right).eq(&[3, 500, 999]));
+        assert!(right.intersection_iter(&left).eq(&[3, 500, 999]));
+
+        let evens: Tree<u32> = (0..1000).step_by(2).collect::<Vec<_>>().into();
+        let threes: Tree<u32> = (0..1000).step_by(3).collect::<Vec<_>>().into();
+        let sixes = evens.intersection(&threes);
+        assert!(sixes.validate().is_ok());
+        assert!(sixes.iter().copied().eq((0..1000).step_by(6)));
+
+        let mut twos: Tree<u32> = Tree::new();
+        twos.set_duplicate_policy(DuplicatePolicy::KeepBoth);
+        for value in [2, 2, 2, 5] {
+            twos.insert(value);
+        }
+        let mut other: Tree<u32> = Tree::new();
+        other.set_duplicate_policy(DuplicatePolicy::KeepBoth);
+        for value in [1, 2, 2] {
+            other.insert(value);
+        }
+        assert!(twos.intersection_iter(&other).eq(&[2, 2]));
+        assert_eq!(twos.intersection_iter(&crate::avl![]).next(), None);
+    }
+
+    #[test]
+    fn difference() {
+        let left = crate::avl![1, 2, 3, 4, 5];
+        let right = crate::avl![0, 2, 4, 6];
+        assert!(left.difference_iter(&right).eq(&[1, 3, 5]));
+        assert!(right.difference_iter(&left).eq(&[0, 6]));
+        assert_eq!(left.difference(&right).to_vec(), [1, 3, 5]);
+
+        assert!(left.symmetric_difference_iter(&right).eq(&[0, 1, 3, 5, 6]));
+        let both = left.symmetric_difference(&right);
+        assert!(both.validate().is_ok());
+        assert_eq!(both.to_vec(), [0, 1, 3, 5, 6]);
+    }
+
+    #[test]
+    fn is_subset() {
+        let small = crate::avl![2, 4];
+        let large = crate::avl![1, 2, 3, 4];
+        assert!(small.is_subset(&large));
+        assert!(!large.is_subset(&small));
+        assert!(large.is_superset(&small));
+        assert!(!crate::avl![2, 5].is_subset(&large));
+        assert!(crate::avl![].is_subset(&small));
+
+        assert!(small.is_disjoint(&crate::avl![1, 3, 5]));
+        assert!(!small.is_disjoint(&large));
+    }
+
+    #[test]
+    fn split_at_rank() {
+        use binary_search::{Count, ShrinkPolicy, Tree};
+
+        for policy in [ShrinkPolicy::Aggressive, ShrinkPolicy::Never] {
+            for len in 0..40 {
+                for k in 0..=len + 1 {
+                    let mut tree: Tree<usize, u32, Count> = Tree::new();
+                    tree.set_shrink_policy(policy);
+                    for value in (0..len).rev() {
+                        tree.insert(value);
+                    }
+                    let handles: Vec<_> = (0..len).filter_map(|n| tree.handle(n)).collect();
+                    let rest = tree.split_at_rank(k);
+                    assert!(tree.validate().is_ok());
+                    assert!(rest.validate().is_ok());
+                    assert!(tree.iter().copied().eq(0..k.min(len)));
+                    assert!(rest.iter().copied().eq(k.min(len)..len));
+
+                    // Values were inserted in descending order, so a rebuilt
+                    // tree puts most of them at other indices. Handles never
+                    // resolve to another value, and with `Never` the values
+                    // that are kept are never moved.
+                    for handle in handles {
+                        let value = tree.get_handle(handle);
+                        let expected = len - 1 - handle.index();
+                        match policy {
+                            ShrinkPolicy::Never if expected < k => {
+                                assert_eq!(value, Some(&expected));
+                            }
+                            _ => assert!(value.is_none() || value == Some(&expected)),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn join() {
+        use binary_search::{Count, Tree};
+
+        for left_len in 0..20 {
+            for right_len in [0, 1, 2, 5, 30] {
+                let mut left: Tree<usize, u32, Count> = Tree::new();
+                let mut right: Tree<usize, u32, Count> = Tree::new();
+                for value in 0..left_len {
+                    left.insert(value);
+                }
+                for value in left_len + 1..=left_len + right_len {
+                    right.insert(value);
+                }
+
+                let Ok(tree) = Tree::join(left, left_len, right) else {
+                    panic!("values are disjoint");
+                };
+                assert!(tree.validate().is_ok());
+                assert!(tree.iter().copied().eq(0..=left_len + right_len));
+            }
+        }
+
+        let Err((left, pivot, right)) = Tree::join(crate::avl![1, 5], 5, crate::avl![7]) else {
+            panic!("5 is in the left tree");
+        };
+        assert_eq!((left.len(), pivot, right.len()), (2, 5, 1));
+
+        // LEFT is copied into RIGHT's storage, so its handles stop resolving.
+        let left = crate::avl![1];
+        let handle = left.handle(left.contains(1).unwrap()).unwrap();
+        let Ok(tree) = Tree::join(left, 5, crate::avl![10, 11, 12]) else {
+            panic!("values are disjoint");
+        };
+        assert_eq!(tree.get_handle(handle), None);
+    }
+
+    #[test]
+    fn retain() {
+        let mut tree = crate::avl![1, 2, 3, 4, 5, 6];
+        let four = tree.contains(4).unwrap();
+        tree.retain(|value| value % 2 == 0);
+        assert_eq!(tree.to_vec(), [2, 4, 6]);
+        assert_eq!(tree.get(four), Some(&4));
+
+        tree.retain_mut(|value| {
+            *value *= 10;
+            *value < 50
+        });
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.to_vec(), [20, 40]);
+    }
+
+    #[test]
+    fn truncate_smallest() {
+        use binary_search::{Count, ShrinkPolicy, Tree};
+
+        let mut tree: Tree<u32, u32, Count> = Tree::new();
+        for value in [50, 10, 40, 20, 30, 60] {
+            tree.insert(value);
+        }
+        tree.truncate_largest(4);
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.to_vec(), [30, 40, 50, 60]);
+
+        tree.truncate_smallest(3);
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.to_vec(), [30, 40, 50]);
+
+        tree.truncate_largest(10);
+        assert_eq!(tree.len(), 3);
+        tree.truncate_smallest(0);
+        assert!(tree.is_empty());
+
+        // Keeping few of the largest values moves them into a new tree.
+        let mut tree: Tree<u32, u32, Count> = Tree::new();
+        for value in (0..20).rev() {
+            tree.insert(value);
+        }
+        let handles: Vec<_> = (0..20).filter_map(|n| tree.handle(n)).collect();
+        tree.truncate_largest(3);
+        assert_eq!(tree.to_vec(), [17, 18, 19]);
+        for handle in handles {
+            let value = tree.get_handle(handle);
+            assert!(value.is_none() || value == Some(&(19 - handle.index() as u32)));
+        }
+
+        // Keeping most of them leaves them, and their handles, in place.
+        let mut tree: Tree<u32, u32, Count> = Tree::new();
+        for value in (0..20).rev() {
+            tree.insert(value);
+        }
+        let handle = tree.handle(0).unwrap();
+        tree.truncate_largest(15);
+        assert_eq!(tree.get_handle(handle), Some(&19));
+
+        // With `Never` the kept values stay in place either way.
+        for k in [0, 3, 15, 20] {
+            let mut tree: Tree<u32, u32, Count> = Tree::new();
+            tree.set_shrink_policy(ShrinkPolicy::Never);
+            for value in (0..20).rev() {
+                tree.insert(value);
+            }
+            let handles: Vec<_> = (0..20).filter_map(|n| tree.handle(n)).collect();
+            tree.truncate_largest(k);
+            assert!(tree.validate().is_ok());
+            assert!(tree.iter().copied().eq(20 - k as u32..20));
+            for handle in &handles[..k] {
+                assert_eq!(
+                    tree.get_handle(*handle),
+                    Some(&(19 - handle.index() as u32))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_tree() {
+        use binary_search::bounded::{BoundedTree, Eviction};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let evicted = Rc::new(RefCell::new(vec![]));
+        let mut top = BoundedTree::new(3, Eviction::DropSmallest);
+        let log = Rc::clone(&evicted);
+        top.set_on_evict(move |value| log.borrow_mut().push(value));
+
+        for score in [5, 1, 9, 7, 3, 9] {
+            top.insert(score);
+        }
+        assert!(top.iter().eq(&[5, 7, 9]));
+        assert_eq!(*evicted.borrow(), [1, 3]);
+        assert!(!top.insert(2));
+        assert_eq!(top.first(), Some(&5));
+
+        let mut bottom = BoundedTree::new(2, Eviction::DropLargest);
+        for score in [5, 1, 9, 3] {
+            bottom.insert(score);
+        }
+        assert!(bottom.iter().eq(&[1, 3]));
+
+        let mut first = BoundedTree::new(2, Eviction::Reject);
+        assert!(first.insert(5) && first.insert(1));
+        assert!(!first.insert(0));
+        assert_eq!(first.pop_last(), Some(5));
+        assert!(first.insert(0));
+        assert!(first.iter().eq(&[0, 1]));
+    }
+
+    #[test]
+    fn partition() {
+        let tree = crate::avl![1, 2, 3, 4, 5, 6, 7];
+        let (even, odd) = tree.partition(|value| value % 2 == 0);
+        assert!(even.validate().is_ok());
+        assert!(odd.validate().is_ok());
+        assert_eq!(even.to_vec(), [2, 4, 6]);
+        assert_eq!(odd.to_vec(), [1, 3, 5, 7]);
+
+        // With `Never` the matching values stay at their indices.
+        let mut tree: binary_search::Tree<i32> = binary_search::Tree::new();
+        tree.set_shrink_policy(binary_search::ShrinkPolicy::Never);
+        for value in (1..=7).rev() {
+            tree.insert(value);
+        }
+        let handles: Vec<_> = (0..7).filter_map(|n| tree.handle(n)).collect();
+        let (even, odd) = tree.partition(|value| value % 2 == 0);
+        assert!(even.validate().is_ok());
+        assert!(odd.validate().is_ok());
+        assert_eq!(even.to_vec(), [2, 4, 6]);
+        assert_eq!(odd.to_vec(), [1, 3, 5, 7]);
+        for handle in handles {
+            let value = 7 - handle.index() as i32;
+            let expected = (value % 2 == 0).then_some(&value);
+            assert_eq!(even.get_handle(handle), expected);
+        }
+    }
+
+    #[test]
+    fn remove_all_sorted() {
+        use binary_search::{ShrinkPolicy, Tree};
+
+        // Few keys are removed one by one, keeping indices.
+        let mut tree: Tree<u32> = (0..100).collect::<Vec<_>>().into();
+        let index = tree.contains(50).unwrap();
+        assert_eq!(tree.remove_all_sorted([3, 7, 200]), 2);
+        assert_eq!(tree.contains(50), Some(index));
+
+        // Many keys are removed in one walk, and the tree is rebuilt.
+        let handle = tree.handle(index).unwrap();
+        assert_eq!(tree.remove_all_sorted((0..100).step_by(3)), 33);
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.get_handle(handle), None);
+        assert!(tree
+            .iter()
+            .copied()
+            .eq((0..100).filter(|n| n % 3 != 0 && *n != 7)));
+
+        // Unsorted keys are still removed.
+        assert_eq!(tree.remove_all_sorted([8, 1, 5]), 3);
+        assert_eq!(tree.len(), 62);
+
+        // Under `Never`, every value keeps its index.
+        let mut tree: Tree<u32> = (0..100).collect::<Vec<_>>().into();
+        tree.set_shrink_policy(ShrinkPolicy::Never);
+        let index = tree.contains(99).unwrap();
+        assert_eq!(tree.remove_all_sorted(0..60), 60);
+        assert_eq!(tree.get(index), Some(&99));
+        assert!(tree.iter().copied().eq(60..100));
+
+        // Slots that were never removed from don't resolve once they're
+        // refilled after a rebuild either.
+        let mut tree: Tree<u32> = (0..10).collect::<Vec<_>>().into();
+        let handle = tree.handle(9).unwrap();
+        assert_eq!(tree.remove_all_sorted(0..10), 10);
+        for value in 10..20 {
+            tree.insert(value);
+        }
+        assert_eq!(tree.get(9), Some(&19));
+        assert_eq!(tree.get_handle(handle), None);
+    }
+
+    #[test]
+    fn compare() {
+        use binary_search::{Compare, Compared, Tree};
+        use std::cmp::Ordering;
+
+        struct ByLen;
+
+        impl Compare<&str> for ByLen {
+            fn compare(a: &&str, b: &&str) -> Ordering {
+                a.len().cmp(&b.len())
+            }
+        }
+
+        let mut tree: Tree<Compared<&str, ByLen>> = Tree::new();
+        for word in ["pear", "fig", "banana", "kiwi"] {
+            tree.insert(Compared::new(word));
+        }
+        let words: Vec<&str> = tree.iter().map(|word| word.value).collect();
+        assert_eq!(words, ["fig", "pear", "banana"]);
+        assert!(tree.contains(Compared::new("plum")).is_some());
+
+        let natural: Tree<Compared<&str>> = Tree::from(["pear", "fig"].map(Compared::new));
+        assert_eq!(natural.iter().next().map(|word| word.len()), Some(3));
+    }
+
+    #[test]
+    fn tree_by_key() {
+        use binary_search::by_key::TreeByKey;
+
+        struct User {
+            id: u32,
+            name: &'static str,
+        }
+
+        let mut users = TreeByKey::new(|user: &User| user.id);
+        users.insert(User { id: 7, name: "ada" });
+        users.insert(User { id: 3, name: "bo" });
+        let old = users.insert(User { id: 7, name: "cy" });
+        assert_eq!(old.map(|user| user.name), Some("ada"));
+
+        assert_eq!(users.len(), 2);
+        assert!(users.contains_key(&3));
+        assert_eq!(users.get(&7).map(|user| user.name), Some("cy"));
+        let ids: Vec<u32> = users.iter().map(|user| user.id).collect();
+        assert_eq!(ids, [3, 7]);
+
+        assert_eq!(users.remove(&3).map(|user| user.name), Some("bo"));
+        assert!(!users.contains_key(&3));
+    }
+
+    #[test]
+    fn reversed() {
+        use std::cmp::Reverse;
+
+        let mut tree = crate::avl![3, 1, 4, 5, 9, 2, 6];
+        tree.remove(4);
+        let nine = tree.contains(9).unwrap();
+
+        let mut reversed = tree.reversed();
+        assert!(reversed.validate().is_ok());
+        assert_eq!(reversed.get(nine), Some(&Reverse(9)));
+        let values: Vec<u32> = reversed.iter().map(|value| value.0).collect();
+        assert_eq!(values, [9, 6, 5, 3, 2, 1]);
+        let range: Vec<u32> = reversed.range(Reverse(5)..).map(|value| value.0).collect();
+        assert_eq!(range, [5, 3, 2, 1]);
+
+        reversed.insert(Reverse(7));
+        let tree = reversed.unreversed();
+        assert!(tree.validate().is_ok());
+        assert_eq!(tree.to_vec(), [1, 2, 3, 5, 6, 7, 9]);
+    }
+
+    #[test]
+    fn total_f64() {
+        use binary_search::{TotalF64, Tree};
+
+        let mut tree: Tree<TotalF64> = Tree::new();
+        for value in [2.5, f64::NAN, -1.0, 0.0, -0.0, f64::NEG_INFINITY, 2.5] {
+            tree.insert(value.into());
+        }
+        assert_eq!(tree.len(), 6);
+        let values: Vec<f64> = tree.iter().map(|value| value.value).collect();
+        assert_eq!(values[..5], [f64::NEG_INFINITY, -1.0, -0.0, 0.0, 2.5]);
+        assert!(values[2].is_sign_negative());
+        assert!(values[5].is_nan());
+        assert!(tree.contains(TotalF64::new(f64::NAN)).is_some());
+    }
+
+    #[test]
+    fn keep_both_order() {
+        use binary_search::{Compare, Compared, DuplicatePolicy, Tree};
+        use std::cmp::Ordering;
+
+        // Ordered by the key only, so equal values can be told apart.
+        struct ByKey;
+
+        impl Compare<(u32, char)> for ByKey {
+            fn compare(a: &(u32, char), b: &(u32, char)) -> Ordering {
+                a.0.cmp(&b.0)
+            }
+        }
+
+        let mut tree: Tree<Compared<(u32, char), ByKey>> = Tree::new();
+        tree.set_duplicate_policy(DuplicatePolicy::KeepBoth);
+        for (n, tag) in ('a'..='p').enumerate() {
+            tree.insert(Compared::new((n as u32 % 3, tag)));
+        }
+        assert_eq!(tree.validate(), Ok(()));
+
+        let tags: String = tree.iter().map(|value| value.1).collect();
+        assert_eq!(tags, "adgjmpbehkncfilo");
+    }
+
+    #[test]
+    fn handles_after_moves() {
+        use binary_search::{Count, Tree};
+
+        // Returns a tree of 0..LEN, with a handle to VALUE, which is kept
+        // by every operation below.
+        let tree = |len: u32, value: u32| {
+            let mut tree: Tree<u32, u32, Count> = Tree::new();
+            for n in (0..len).rev() {
+                tree.insert(n);
+            }
+            let handle = tree.handle(tree.contains(value).unwrap()).unwrap();
+            (tree, handle)
+        };
+
+        let (mut compacted, handle) = tree(10, 9);
+        compacted.remove(0);
+        compacted.compact();
+        assert_eq!(compacted.get_handle(handle), None);
+
+        let (mut extended, handle) = tree(10, 9);
+        extended.extend_from_sorted(0..100);
+        assert_eq!(extended.get_handle(handle), None);
+
+        let (mut removed, handle) = tree(100, 99);
+        removed.remove_all_sorted(0..50);
+        assert_eq!(removed.get_handle(handle), None);
+
+        let (mut concatenated, handle) = tree(10, 9);
+        let other = Tree::from_sorted_unchecked((10..100).collect());
+        assert!(concatenated.concat_disjoint(other).is_ok());
+        assert_eq!(concatenated.get_handle(handle), None);
+
+        let (mut split, handle) = tree(10, 0);
+        let rest = split.split_at_rank(2);
+        assert_eq!(split.get_handle(handle), None);
+        assert_eq!(rest.len(), 8);
+
+        let (mut smallest, handle) = tree(10, 0);
+        smallest.truncate_smallest(2);
+        assert_eq!(smallest.get_handle(handle), None);
+
+        let (mut largest, handle) = tree(10, 9);
+        largest.truncate_largest(2);
+        assert_eq!(largest.get_handle(handle), None);
+    }
+
+    #[test]
+    fn transaction_never_reuses() {
+        use binary_search::{ShrinkPolicy, Tree};
+
+        let mut tree: Tree<u32> = Tree::from([1, 2]);
+        tree.set_shrink_policy(ShrinkPolicy::Never);
+        let result: Result<usize, ()> = tree.transaction(|txn| {
+            let index = txn.insert(3).unwrap();
+            txn.remove(3);
+            Ok(index)
+        });
+        let index = result.unwrap();
+        assert_ne!(tree.insert(4), Some(index));
+        assert_eq!(tree.get(index), None);
+
+        // Slots added by a rolled back transaction aren't reused either.
+        let result: Result<(), usize> = tree.transaction(|txn| Err(txn.insert(5).unwrap()));
+        let index = result.unwrap_err();
+        assert_eq!(tree.get(index), None);
+        assert_ne!(tree.insert(6), Some(index));
+    }
+
+    #[test]
+    fn node_size() {
+        use binary_search::{SlotIndex, Tree};
+
+        // The bytes used by one slot, as reported for unused capacity.
+        fn slot_size<I: SlotIndex>() -> usize {
+            let tree: Tree<u32, I> = Tree::with_capacity(64);
+            tree.health().wasted_bytes / tree.capacity()
+        }
+
+        // Three links of I, a height and the value, with no room spent on
+        // telling links apart from missing ones.
+        assert!(slot_size::<u32>() <= 24);
+        assert!(slot_size::<u16>() < slot_size::<u32>());
+        assert!(slot_size::<u32>() < slot_size::<usize>());
+    }
+
+    #[test]
+    fn tree_view_corrupt() {
+        use binary_search::{SnapshotError, Tree, TreeView};
+
+        let tree: Tree<u32> = (0..30).collect::<Vec<_>>().into();
+        let bytes = tree.to_bytes(|value, bytes| bytes.extend(value.to_le_bytes()));
+        // Doesn't panic on values of the wrong length.
+        let decode = |b: &[u8]| b.iter().map(|&n| u32::from(n)).sum::<u32>();
+
+        for len in 18..bytes.len() {
+            assert!(TreeView::new(&bytes[..len], decode).is_err());
+        }
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(
+            TreeView::new(&longer, decode).err(),
+            Some(SnapshotError::Length)
+        );
+
+        // Every view that's created can be searched without panicking.
+        for n in 18..bytes.len() {
+            for bit in 0..8 {
+                let mut corrupt = bytes.clone();
+                corrupt[n] ^= 1 << bit;
+                if let Ok(view) = TreeView::new(&corrupt, decode) {
+                    assert!(view.range(..).count() <= view.len());
+                    let _ = view.get(&7);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tree_by_key_descents() {
+        use binary_search::by_key::TreeByKey;
+        use std::cell::Cell;
+        use std::cmp::Ordering;
+
+        thread_local! {
+            static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        // A key that counts how many times it's compared.
+        #[derive(PartialEq, Eq)]
+        struct Key(u32);
+        impl PartialOrd for Key {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Key {
+            fn cmp(&self, other: &Self) -> Ordering {
+                COMPARISONS.with(|n| n.set(n.get() + 1));
+                self.0.cmp(&other.0)
+            }
+        }
+
+        // 1023 values in ascending order make a perfect tree of height 9.
+        let mut set = TreeByKey::new(|value: &u32| Key(*value));
+        for value in 0..1023 {
+            set.insert(value);
+        }
+
+        // A new key and an existing one each take a single descent.
+        COMPARISONS.with(|n| n.set(0));
+        assert_eq!(set.insert(1023), None);
+        assert!(COMPARISONS.with(Cell::get) <= 10);
+
+        COMPARISONS.with(|n| n.set(0));
+        assert_eq!(set.insert(500), Some(500));
+        assert!(COMPARISONS.with(Cell::get) <= 10);
+        assert_eq!(set.len(), 1024);
+        assert!(set.iter().copied().eq(0..1024));
+    }
 }